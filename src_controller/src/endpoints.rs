@@ -1,40 +1,123 @@
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::BufReader;
+use std::path::Path;
 use dotenvy::dotenv;
 use rand::prelude::IndexedRandom;
 use serde_json::json;
-use crate::generators::gen_names::gen_characters;
+use crate::generators::gen_names::{assign_homes, gen_characters};
+use crate::generators::gen_place_names::medievalise_map;
 use crate::generators::gen_places::fetch_map;
+use crate::io::geojson::{read_geojson, write_geojson};
 use crate::io::io::{read_map_from_file, write_map_to_file};
-use crate::types::{ownership_to_json_map, Event};
+use crate::types::{ownership_to_json_map, ClusterParams, Event, MapParams, RosterParams};
 use crate::utils::cluster::cluster_locations;
+use crate::utils::dynasty::{gen_relationships, line_of_succession};
+use crate::utils::factions::factions;
+use crate::utils::ids::upgrade_legacy;
+use crate::utils::territory::faction_territories;
+use crate::utils::validate::validate_map;
 use crate::visualisers::viz_places::viz_map;
 
-pub async fn init_map(name: String, live: bool) -> String {
+/// Editable GeoJSON copy of the last live map, loaded in preference to map.json
+const GEOJSON_MAP_PATH: &str = "map.geojson";
 
-    let map = {
+/// Build a realm for the client. A `live` map is fetched fresh for `cities`;
+/// otherwise the edited map.geojson is loaded, or map.json when there is none.
+pub async fn init_map(
+    cities: Vec<String>,
+    live: bool,
+    params: MapParams,
+    cluster_params: ClusterParams,
+    roster_params: RosterParams,
+    seed: u64,
+    llm_names: bool,
+) -> String {
+
+    // Factions assigned by hand in an edited GeoJSON, keyed by place id
+    let mut imported_ownership: HashMap<String, String> = HashMap::new();
+
+    let mut map = {
         if live {
             dotenv().ok();
-            println!("Fetching up to {} attractions in {}...", 10, name);
-            // Destructure the returned tuple
-            let map = fetch_map(&*name, 10, 200.0).await.unwrap();
+            println!("Fetching up to {} places in each of {}...", params.count, cities.join(", "));
+            let map = match fetch_map(&cities, &params).await {
+                Ok(map) => map,
+                Err(e) => {
+                    return json!({
+                        "INIT_MAP": { "error": format!("Could not fetch a map: {}", e) }
+                    }).to_string();
+                }
+            };
             println!("{}", map);
 
             let _ = write_map_to_file(&map, "map.json");
 
             map
         }
+        else if Path::new(GEOJSON_MAP_PATH).exists() {
+            // Designers edit kingdoms in QGIS; prefer their GeoJSON over the raw map
+            let (map, ownership) = read_geojson(GEOJSON_MAP_PATH).unwrap();
+            imported_ownership = ownership_to_json_map(ownership);
+            map
+        }
         else {
             read_map_from_file("map.json").unwrap()
         }
     };
-    let characters = gen_characters();
+
+    // Check the map before anything indexes into it, whether fetched or loaded
+    let report = validate_map(&mut map);
+    print!("{}", report);
+    if !report.is_usable() {
+        return json!({
+            "INIT_MAP": { "error": format!("Map could not be used: {:?}", report.findings) }
+        }).to_string();
+    }
+
+    let events = generate_start_events();
+    let mut characters = match gen_characters(cluster_params.faction_count, &roster_params, &events, seed) {
+        Ok(characters) => characters,
+        Err(e) => {
+            return json!({
+                "INIT_MAP": { "error": format!("Could not build rosters: {}", e) }
+            }).to_string();
+        }
+    };
     for character in &characters {
         println!("name: {}. faction: {:?}", character.name, character.faction);
     }
-    let ownership = cluster_locations(&map);
+    let (mut ownership, clustering) = cluster_locations(&map, &cluster_params, seed);
+    println!(
+        "Clustered into {:?} after {} iteration(s) (converged: {}, inertia: {:.4}, balance: {:.2} size / {:.2} spread)",
+        clustering.cluster_sizes, clustering.iterations, clustering.converged, clustering.inertia,
+        clustering.size_balance, clustering.spread_balance
+    );
+    if let Some(min_balance) = cluster_params.min_balance && !clustering.is_balanced(min_balance) {
+        return json!({
+            "INIT_MAP": { "error": "Factions are too lopsided for this map", "clustering": clustering }
+        }).to_string();
+    }
+    for (place, faction) in ownership.iter_mut() {
+        if let Some(imported) = imported_ownership.get(&place.id) {
+            *faction = imported.clone();
+        }
+    }
+    let ownership = medievalise_map(&mut map, ownership, seed, llm_names).await;
+    assign_homes(&mut characters, &ownership, seed);
+    let relationships = gen_relationships(&characters, seed);
+    let succession: HashMap<&str, Vec<String>> = factions()
+        .iter()
+        .map(|f| (f.id.as_str(), line_of_succession(&f.id, &characters, &relationships)))
+        .filter(|(_, line)| !line.is_empty())
+        .collect();
+
+    if live {
+        let _ = write_geojson(&map, &ownership, GEOJSON_MAP_PATH);
+    }
 
-    viz_map(&map, &ownership).unwrap();
+    let territories = faction_territories(&map, &ownership);
+    viz_map(&map, &ownership, &territories).unwrap();
 
     let ownership_map = ownership_to_json_map(ownership);
 
@@ -44,8 +127,14 @@ pub async fn init_map(name: String, live: bool) -> String {
         "INIT_MAP": {
             "map": map,
             "characters": characters,
+            "relationships": relationships,
+            "succession": succession,
             "ownership": ownership_map,
-            "events": generate_start_events()
+            "factions": factions().iter().take(clustering.cluster_sizes.len()).collect::<Vec<_>>(),
+            "clustering": clustering,
+            "territories": territories,
+            "events": events,
+            "seed": seed
         }
     });
 
@@ -71,5 +160,8 @@ pub fn generate_start_events() -> Vec<Event> {
         .choose(&mut rng)
         .expect("No event groups found");
 
-    chosen_group.clone()
+    // The bundled events are keyed by name; give them ids like everything else
+    let mut events = chosen_group.clone();
+    upgrade_legacy(&mut events, &mut []);
+    events
 }
\ No newline at end of file
@@ -2,13 +2,22 @@ use rand::Rng;
 use rand::rngs::StdRng;
 use rand::SeedableRng;
 use rand::prelude::{IndexedRandom, SliceRandom};
+use std::collections::{HashMap, HashSet};
 
+use crate::generators::gen_names::NameGenerator;
 use crate::interval::plot::add_constraint_and_get_interval;
-use crate::solver::solve::isPossible;
-use crate::types::{Character, Effect, Event};
+use crate::solver::solve::is_possible_under;
+use crate::types::{
+    Archetype, Character, Condition, Effect, EffectKind, Event, EventType, FactionMix, NameParams, ParticipantRule,
+    RelationKind, Role, TimelineRules,
+};
+use crate::utils::dynasty::{current_rulers, the_dead};
+use crate::utils::event_types::event_types;
+use crate::utils::ids::fresh_id;
+use crate::utils::names::names;
 use crate::utils::prompt::get_name_and_description;
 
-/// Always succeeds by inserting NEW_EVENT before the earliest reachable node in the DAG
+/// Always succeeds by inserting the new event before the earliest reachable node in the DAG
 fn safe_prepend(events: &mut Vec<Event>) -> (Vec<String>, (f32, f32), i32) {
     // Find the event with no incoming edges (earliest in topological order)
     let mut with_incoming: std::collections::HashSet<String> = std::collections::HashSet::new();
@@ -20,12 +29,12 @@ fn safe_prepend(events: &mut Vec<Event>) -> (Vec<String>, (f32, f32), i32) {
 
     let candidate = events
         .iter()
-        .find(|e| !with_incoming.contains(&e.name))
+        .find(|e| !with_incoming.contains(&e.id))
         .cloned();
 
     if let Some(target) = candidate {
         let end_time = if target.start > 0.05 { target.start } else { 0.05 };
-        let before_list = vec![target.name.clone()];
+        let before_list = vec![target.id.clone()];
         ((before_list), (0.0, end_time as f32), 0)
     } else {
         // In degenerate case (all events interdependent), create isolated event
@@ -36,18 +45,19 @@ fn safe_prepend(events: &mut Vec<Event>) -> (Vec<String>, (f32, f32), i32) {
 fn maybe_transitive_insert(
     mut events: Vec<Event>,
     rng: &mut StdRng,
-    before_event_name: &str,
+    before_event_id: &str,
+    new_event_id: &str,
 ) -> (Vec<Event>, Vec<String>) {
-    let mut before_list = vec![before_event_name.to_string()];
+    let mut before_list = vec![before_event_id.to_string()];
 
     if rng.gen_bool(0.75) {
-        let target_b = before_event_name;
+        let target_b = before_event_id;
         if let Some(a_idx) = events.iter().position(|e| e.before.contains(&target_b.to_string())) {
             let a_name = events[a_idx].name.clone();
             events[a_idx].before.retain(|b| b != target_b);
-            events[a_idx].before.push("NEW_EVENT".to_string());
+            events[a_idx].before.push(new_event_id.to_string());
             before_list = vec![target_b.to_string()];
-            println!("🔁 Transitive insertion: {} < NEW_EVENT < {}", a_name, target_b);
+            println!("🔁 Transitive insertion: {} < {} < {}", a_name, new_event_id, target_b);
         } else {
             println!("ℹ️ No suitable transitive pair found; flat insertion instead.");
         }
@@ -58,26 +68,291 @@ fn maybe_transitive_insert(
     (events, before_list)
 }
 
+/// What a new event's effects can act on
+struct World<'a> {
+    /// Id the new event will have
+    event_id: &'a str,
+    events: &'a [Event],
+    characters: &'a [Character],
+    rules: &'a TimelineRules,
+    place_archetypes: &'a HashMap<String, Archetype>,
+}
+
+/// Ids of everyone in exile once `events` have played out in order of `start`
+fn exiles(events: &[Event]) -> HashSet<String> {
+    let mut ordered: Vec<&Event> = events.iter().collect();
+    ordered.sort_by(|a, b| a.start.total_cmp(&b.start));
+    let mut exiled = HashSet::new();
+    for effect in ordered.iter().flat_map(|e| &e.effects) {
+        match effect {
+            Effect::Exile(id) => {
+                exiled.insert(id.clone());
+            }
+            Effect::Return(id) => {
+                exiled.remove(id);
+            }
+            _ => {}
+        }
+    }
+    exiled
+}
+
+/// Living characters free to marry: no priests, and nobody already wed
+fn singles<'a>(world: &World, living: &[&'a Character]) -> Vec<&'a Character> {
+    let wed: HashSet<&String> = world
+        .rules
+        .relationships
+        .iter()
+        .filter(|r| r.kind == RelationKind::Spouse)
+        .flat_map(|r| [&r.from, &r.to])
+        .chain(world.events.iter().flat_map(|e| &e.effects).flat_map(|effect| match effect {
+            Effect::Marriage(a, b) => vec![a, b],
+            _ => vec![],
+        }))
+        .collect();
+    living
+        .iter()
+        .filter(|c| c.role != Some(Role::Priest) && !wed.contains(&c.id))
+        .copied()
+        .collect()
+}
+
+/// Whoever the line has reached once a faction's ruler is dead, if not yet crowned
+fn uncrowned_heirs(world: &World) -> Vec<String> {
+    let crowned: HashSet<&String> = world
+        .events
+        .iter()
+        .flat_map(|e| &e.effects)
+        .filter_map(|effect| match effect {
+            Effect::Coronation(id) => Some(id),
+            _ => None,
+        })
+        .collect();
+    current_rulers(world.characters, &world.rules.relationships, world.events)
+        .into_values()
+        .flatten()
+        .filter(|id| {
+            let is_ruler = world.characters.iter().any(|c| &c.id == id && c.role == Some(Role::Ruler));
+            !is_ruler && !crowned.contains(id)
+        })
+        .collect()
+}
+
+/// Whether an effect of `kind` has anything to act on, e.g. someone alive to
+/// die or a place some other faction could take. Only looks for candidates;
+/// `make_effect` does the choosing.
+fn can_make(kind: EffectKind, world: &World) -> bool {
+    let dead = the_dead(world.characters, world.events);
+    let living: Vec<&Character> = world.characters.iter().filter(|c| !dead.contains(&c.id)).collect();
+    match kind {
+        EffectKind::Death | EffectKind::Injury | EffectKind::Birth => !living.is_empty(),
+        EffectKind::Resurrection => world.characters.iter().any(|c| dead.contains(&c.id)),
+        EffectKind::Exile | EffectKind::Return => {
+            let exiled = exiles(world.events);
+            living.iter().any(|c| exiled.contains(&c.id) != (kind == EffectKind::Exile))
+        }
+        EffectKind::Marriage => singles(world, &living).len() >= 2,
+        EffectKind::Coronation => !uncrowned_heirs(world).is_empty(),
+        EffectKind::Transfer => world.rules.ownership.values().collect::<HashSet<_>>().len() >= 2,
+    }
+}
+
+/// Make an effect of `kind`, or `None` when there is nothing for it to act on.
+/// Places of the `preferred` archetype are taken first when the client sent
+/// archetypes. Anyone born is added to `newborns`.
+fn make_effect(
+    kind: EffectKind,
+    world: &World,
+    preferred: Option<Archetype>,
+    rng: &mut StdRng,
+    newborns: &mut Vec<Character>,
+) -> Option<Effect> {
+    let dead = the_dead(world.characters, world.events);
+    let living: Vec<&Character> = world.characters.iter().filter(|c| !dead.contains(&c.id)).collect();
+    match kind {
+        EffectKind::Death => Some(Effect::Death(living.choose(rng)?.id.clone())),
+        EffectKind::Injury => Some(Effect::Injury(living.choose(rng)?.id.clone())),
+        EffectKind::Resurrection => {
+            let departed: Vec<&Character> = world.characters.iter().filter(|c| dead.contains(&c.id)).collect();
+            Some(Effect::Resurrection(departed.choose(rng)?.id.clone()))
+        }
+        EffectKind::Exile | EffectKind::Return => {
+            let exiled = exiles(world.events);
+            let leaving = kind == EffectKind::Exile;
+            let who: Vec<&&Character> = living.iter().filter(|c| exiled.contains(&c.id) != leaving).collect();
+            let id = who.choose(rng)?.id.clone();
+            Some(if leaving { Effect::Exile(id) } else { Effect::Return(id) })
+        }
+        EffectKind::Marriage => {
+            let single = singles(world, &living);
+            let couple: Vec<&&Character> = single.sample(rng, 2).collect();
+            match couple[..] {
+                [a, b] => Some(Effect::Marriage(a.id.clone(), b.id.clone())),
+                _ => None,
+            }
+        }
+        EffectKind::Coronation => Some(Effect::Coronation(uncrowned_heirs(world).choose(rng)?.clone())),
+        EffectKind::Birth => {
+            let faction = living.choose(rng)?.faction.clone();
+            let mut generator = NameGenerator::new(names().characters(), &NameParams::default(), rng.random());
+            for c in world.characters {
+                generator.reserve(&c.name);
+            }
+            generator.reserve_events(world.events);
+            let name = generator.generate(&faction)?;
+            // Names can repeat across a long game, so the child's id doesn't come from theirs
+            let child = Character {
+                id: fresh_id("ch", rng),
+                name,
+                faction,
+                born: Some(world.event_id.to_string()),
+                ..Default::default()
+            };
+            let effect = Effect::Birth(child.id.clone());
+            newborns.push(child);
+            Some(effect)
+        }
+        EffectKind::Transfer => {
+            // Hand a place to any faction other than the one it starts with
+            let ownership = &world.rules.ownership;
+            let mut places: Vec<&String> = ownership.keys().collect();
+            places.sort();
+            let suited: Vec<&String> = places
+                .iter()
+                .filter(|p| preferred.is_some() && world.place_archetypes.get(**p).copied() == preferred)
+                .copied()
+                .collect();
+            if !suited.is_empty() {
+                places = suited;
+            }
+            let mut rivals: Vec<&String> = ownership.values().collect();
+            rivals.sort();
+            rivals.dedup();
+            let place = places.choose(rng)?;
+            let others: Vec<&&String> = rivals.iter().filter(|f| **f != &ownership[*place]).collect();
+            let to = others.choose(rng)?;
+            Some(Effect::Transfer { place: place.to_string(), to: to.to_string() })
+        }
+    }
+}
+
+/// Choose who takes part in an event of the rule's shape. Anyone the effects
+/// act on takes part, and a conquest is fought by the conquering faction.
+fn pick_participants(
+    rule: &ParticipantRule,
+    characters: &[Character],
+    effects: &[Effect],
+    rng: &mut StdRng,
+) -> Vec<Character> {
+    // Step 1: Those the effects act on, and the faction they settle on
+    let mut chosen: Vec<Character> = Vec::new();
+    for id in effects.iter().flat_map(|e| e.characters()) {
+        if let Some(c) = characters.iter().find(|c| &c.id == id)
+            && !chosen.iter().any(|x| x.id == c.id)
+        {
+            chosen.push(c.clone());
+        }
+    }
+    let conqueror = effects.iter().find_map(|e| match e {
+        Effect::Transfer { to, .. } if characters.iter().any(|c| &c.faction == to) => Some(to.clone()),
+        _ => None,
+    });
+    let Some(faction) = chosen
+        .first()
+        .map(|c| c.faction.clone())
+        .or(conqueror)
+        .or_else(|| characters.choose(rng).map(|c| c.faction.clone()))
+    else {
+        return chosen;
+    };
+
+    let mut pool: Vec<&Character> = characters
+        .iter()
+        .filter(|c| rule.factions == FactionMix::Mixed || c.faction == faction)
+        .collect();
+    pool.shuffle(rng);
+    let count = rng.random_range(rule.min..=rule.max).max(chosen.len());
+    let free = |chosen: &[Character], c: &Character| !chosen.iter().any(|x| x.id == c.id);
+
+    // Step 2: One of each role the event can't do without, then favoured roles while there is room
+    for (i, role) in rule.roles.iter().chain(&rule.favoured).enumerate() {
+        let required = i < rule.roles.len();
+        if !required && chosen.len() >= count {
+            break;
+        }
+        if required && chosen.iter().any(|c| c.role == Some(*role)) {
+            continue;
+        }
+        if let Some(c) = pool.iter().find(|c| c.role == Some(*role) && free(&chosen, c)) {
+            chosen.push((*c).clone());
+        }
+    }
+
+    // Step 3: Fill up, from whichever faction has fewest so far when mixing
+    while chosen.len() < count {
+        let next = pool
+            .iter()
+            .filter(|c| free(&chosen, c))
+            .min_by_key(|c| chosen.iter().filter(|x| x.faction == c.faction).count());
+        match next {
+            Some(c) => chosen.push((*c).clone()),
+            None => break,
+        }
+    }
+    chosen
+}
+
+/// Generate one event and check the timeline is still possible.
+/// The event's type is drawn from `event_types.json` by weight, among the
+/// types whose required effects can be made. The timeline is checked against
+/// `rules`; when they say who owns which place, conquests can hand places
+/// between factions. `place_names` maps place ids to display names for the
+/// LLM prompt, and `place_archetypes` lets effects favour the type's archetype.
 pub async fn gen_event(
     existing_events: Vec<Event>,
     existing_characters: Vec<Character>,
+    rules: &TimelineRules,
+    place_names: &HashMap<String, String>,
+    place_archetypes: &HashMap<String, Archetype>,
 ) -> (bool, Vec<Event>) {
     let mut rng = StdRng::from_rng(&mut rand::thread_rng());
-    let types = vec!["auxiliary", "catastrophe", "ceremony", "catastrophe", "miracle", "catastrophe", "catastrophe","catastrophe"];
-    let event_type = types.choose(&mut rng).unwrap().to_string();
-
-    // Possible effects
-    let event_effects: Vec<Effect> = match event_type.as_str() {
-        "catastrophe" => {
-            if !existing_characters.is_empty() && rng.random_bool(0.8) {
-                let victim = existing_characters.choose(&mut rng).unwrap().clone();
-                vec![Effect::Death(victim.name)]
-            } else {
-                vec![]
-            }
-        }
-        _ => vec![],
+    // Other events point at this one by id from the start, so renaming it later is safe
+    let new_event_id = fresh_id("ev", &mut rng);
+    let world = World {
+        event_id: &new_event_id,
+        events: &existing_events,
+        characters: &existing_characters,
+        rules,
+        place_archetypes,
     };
+    let usable = |t: &EventType| t.required_effects.iter().all(|k| can_make(*k, &world));
+    let Some(event_type) = event_types().pick(&mut rng, usable) else {
+        eprintln!("⚠️ No event type can be made with these characters and places");
+        return (false, existing_events);
+    };
+
+    // Required effects always happen, the rest by chance
+    let mut event_effects: Vec<Effect> = vec![];
+    let mut newborns: Vec<Character> = vec![];
+    for kind in &event_type.allowed_effects {
+        if event_type.required_effects.contains(kind) || rng.random_bool(event_type.effect_chance) {
+            event_effects.extend(make_effect(*kind, &world, event_type.archetype, &mut rng, &mut newborns));
+        }
+    }
+
+    // A conquest happens at the place taken; anything else somewhere that suits its type
+    let suited: Vec<&String> = place_archetypes
+        .iter()
+        .filter(|(_, a)| Some(**a) == event_type.archetype)
+        .map(|(id, _)| id)
+        .collect();
+    let place = event_effects
+        .iter()
+        .find_map(|e| match e {
+            Effect::Transfer { place, .. } => Some(place.clone()),
+            _ => None,
+        })
+        .or_else(|| suited.choose(&mut rng).map(|id| id.to_string()));
 
     if existing_events.is_empty() {
         panic!("No existing events to place this event before!");
@@ -90,18 +365,18 @@ pub async fn gen_event(
     let mut track = -1;
     let mut updated_events = existing_events.clone();
     let mut before_list = vec!["".to_string()];
-    let mut before_event_name = "".to_string();
+    let mut before_event_id = "".to_string();
 
     while attempt < max_attempts {
         attempt += 1;
-        before_event_name = existing_events.choose(&mut rng).unwrap().name.clone();
+        before_event_id = existing_events.choose(&mut rng).unwrap().id.clone();
 
         let (candidate_events, candidate_before) =
-            maybe_transitive_insert(existing_events.clone(), &mut rng, &before_event_name);
+            maybe_transitive_insert(existing_events.clone(), &mut rng, &before_event_id, &new_event_id);
 
         match add_constraint_and_get_interval(
             candidate_events.clone(),
-            ("NEW_EVENT", &candidate_before[0]),
+            (&new_event_id, &candidate_before[0]),
             "intervals.png",
         ) {
             Ok((iv, tr, up)) => {
@@ -109,7 +384,7 @@ pub async fn gen_event(
                 track = tr;
                 updated_events = up;
                 before_list = candidate_before;
-                println!("✅ Inserted after {} on attempt {}", before_event_name, attempt);
+                println!("✅ Inserted after {} on attempt {}", before_event_id, attempt);
                 break;
             }
             Err(e) => {
@@ -135,69 +410,37 @@ pub async fn gen_event(
 
 
     // --- Character involvement ---
-    let characters: Vec<Character> = if let Some(Effect::Death(name)) =
-        event_effects.iter().find(|e| matches!(e, Effect::Death(_)))
-    {
-        // Death event → include the victim
-        existing_characters
-            .iter()
-            .find(|ch| &ch.name == name)
-            .cloned()
-            .map(|ch| vec![ch])
-            .unwrap_or_else(Vec::new)
-    } else if !existing_characters.is_empty() {
-        let roll: f32 = rng.r#gen(); // 0.0 → 1.0
-        let num_chars = if roll < 0.8 {
-            3 // 40%
-        } else if roll < 0.9{
-            1 // 30%
-        } else {
-            0
-        };
-
-        if num_chars > 0 {
-            // --- Pick a random faction among existing characters ---
-            let faction = existing_characters
-                .choose(&mut rng)
-                .map(|ch| ch.faction.clone())
-                .unwrap_or_default();
-
-            // --- Filter characters by that faction ---
-            let mut same_faction_chars: Vec<Character> = existing_characters
-                .iter()
-                .filter(|ch| ch.faction == faction)
-                .cloned()
-                .collect();
+    // The newborn are part of the cast from their birth on
+    let cast: Vec<Character> = existing_characters.iter().cloned().chain(newborns).collect();
+    let characters = pick_participants(&event_type.participants, &cast, &event_effects, &mut rng);
 
-            // --- Shuffle and truncate to desired size ---
-            same_faction_chars.shuffle(&mut rng);
-            same_faction_chars.truncate(num_chars.min(same_faction_chars.len()));
-            same_faction_chars
-        } else {
-            vec![]
-        }
+    // Rosters from before roles existed can't meet role requirements
+    let requires = if existing_characters.iter().any(|ch| ch.role.is_some()) {
+        event_type.participants.roles.iter().map(|role| Condition::Attends { role: *role }).collect()
     } else {
         vec![]
     };
 
-
     // --- Construct event ---
     let event = Event {
+        id: new_event_id,
         name: "Event".into(),
-        description: format!("A {} event.", event_type),
+        description: format!("A {} event.", event_type.id),
         before: before_list,
         start: interval.0,
         end: interval.1,
-        _type: event_type,
+        _type: event_type.id.clone(),
         characters,
         effects: event_effects,
+        requires,
+        place,
         track: 0.0,
     };
 
-    let event = get_name_and_description(event).await.unwrap();
+    let event = get_name_and_description(event, &updated_events, place_names).await.unwrap();
 
     let combined: Vec<Event> = updated_events.into_iter().chain(std::iter::once(event)).collect();
-    let sat = isPossible(combined.clone(), existing_characters);
+    let sat = is_possible_under(combined.clone(), cast, rules);
 
     (sat, combined)
 }
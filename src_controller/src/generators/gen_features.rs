@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+use reqwest::Client;
+use serde::Deserialize;
+
+use crate::types::{Feature, FeatureKind};
+use crate::utils::geometry::{clip_polygon_to_circle, clip_polyline_to_circle};
+use crate::utils::projection::Projection;
+use crate::utils::simplify::simplify_polyline;
+
+/// Google has no terrain layers, so features come from OpenStreetMap via Overpass
+const OVERPASS_URL: &str = "https://overpass-api.de/api/interpreter";
+
+/// Largest deviation (in game units) allowed when simplifying a feature
+const FEATURE_TOLERANCE: f64 = 0.004;
+/// Most vertices kept per feature after simplification
+const FEATURE_POINT_BUDGET: usize = 64;
+/// Sides of the polygon used to approximate the unit circle when clipping areas
+const CLIP_SEGMENTS: usize = 64;
+
+#[derive(Debug, Deserialize)]
+struct OverpassResponse {
+    elements: Vec<OverpassElement>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OverpassElement {
+    #[serde(default)]
+    tags: HashMap<String, String>,
+    #[serde(default)]
+    geometry: Vec<OverpassPoint>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OverpassPoint {
+    lat: f64,
+    lon: f64,
+}
+
+/// Which layer an OSM way belongs to, if any
+fn feature_kind(tags: &HashMap<String, String>) -> Option<FeatureKind> {
+    let tag = |k: &str| tags.get(k).map(|v| v.as_str());
+    match (tag("waterway"), tag("natural"), tag("landuse")) {
+        (Some("river") | Some("canal"), _, _) => Some(FeatureKind::River),
+        (_, Some("coastline"), _) => Some(FeatureKind::Coastline),
+        (_, Some("water"), _) => Some(FeatureKind::Lake),
+        (_, Some("wood"), _) | (_, _, Some("forest")) => Some(FeatureKind::Forest),
+        _ => None,
+    }
+}
+
+/// Fetch rivers, lakes, forests and coastline within `around_m` metres of each
+/// `(lat, lng)` centre in `areas`, projected into game space and clipped to the
+/// game disc of `radius`. Overlapping areas are fetched in one query, so ways
+/// they share come back once.
+pub async fn fetch_features(
+    client: &Client,
+    projection: &Projection,
+    areas: &[((f64, f64), f64)],
+    radius: f64,
+) -> Result<Vec<Feature>, Box<dyn std::error::Error>> {
+    let mut statements = String::new();
+    for &((lat, lng), around_m) in areas {
+        let a = format!("(around:{:.0},{},{})", around_m, lat, lng);
+        statements.push_str(&format!(
+            "way[\"waterway\"~\"^(river|canal)$\"]{a};\
+            way[\"natural\"~\"^(water|wood|coastline)$\"]{a};\
+            way[\"landuse\"=\"forest\"]{a};"
+        ));
+    }
+    let query = format!("[out:json][timeout:25];({});out geom;", statements);
+
+    let res: OverpassResponse = client
+        .post(OVERPASS_URL)
+        .form(&[("data", query)])
+        .send()
+        .await?
+        .json()
+        .await?;
+
+    let mut features = Vec::new();
+    for element in res.elements {
+        let Some(kind) = feature_kind(&element.tags) else {
+            continue;
+        };
+        let name = element.tags.get("name").cloned();
+        let points: Vec<(f64, f64)> = element
+            .geometry
+            .iter()
+            .map(|p| projection.forward((p.lat, p.lon)))
+            .collect();
+
+        if kind.is_area() {
+            let clipped = clip_polygon_to_circle(&points, radius, CLIP_SEGMENTS);
+            let geometry = simplify_polyline(&clipped, FEATURE_TOLERANCE, FEATURE_POINT_BUDGET);
+            // Areas that end up outside the disc or too small to see are dropped
+            if geometry.len() >= 3 {
+                features.push(Feature { kind, name, geometry });
+            }
+        } else {
+            for piece in clip_polyline_to_circle(&points, radius) {
+                features.push(Feature {
+                    kind,
+                    name: name.clone(),
+                    geometry: simplify_polyline(&piece, FEATURE_TOLERANCE, FEATURE_POINT_BUDGET),
+                });
+            }
+        }
+    }
+
+    Ok(features)
+}
@@ -1,43 +1,396 @@
+use std::collections::{BTreeMap, HashSet};
 use std::fs;
+use std::sync::OnceLock;
+use rand::rngs::StdRng;
 use rand::seq::IndexedRandom;
-use crate::types::{Character};
+use rand::{Rng, SeedableRng};
+use crate::types::{Archetype, Character, Event, NameParams, Ownership, Place, Role, RosterParams};
+use crate::utils::factions::factions;
+use crate::utils::ids::fresh_id;
+use crate::utils::names::names;
 
-pub fn gen_characters() -> Vec<Character> {
-    // Load the JSON file
-    let data = fs::read_to_string("names.json")
-        .expect("Failed to read names.json file");
+/// Marks the start and end of a name in the chain's contexts
+const START: char = '^';
+const END: char = '$';
+/// Draws per name before giving up and reusing an unused name from the pool
+const MAX_ATTEMPTS: usize = 200;
 
-    // Deserialize into a Vec<Character>
-    let all_characters: Vec<Character> = serde_json::from_str(&data)
-        .expect("Failed to parse JSON");
+/// Roles handed out, in turn, to roster slots no quota claims
+const FILL_ROLES: [Role; 3] = [Role::Knight, Role::Merchant, Role::Priest];
+const TRAITS: &[&str] = &[
+    "ambitious", "brave", "craven", "cruel", "cunning", "generous", "greedy", "kind", "pious", "proud",
+    "reckless", "wise",
+];
 
-    // Filter by faction
-    let gnomes: Vec<Character> = all_characters
-        .iter()
-        .filter(|c| c.faction == "g")
-        .cloned()
-        .collect();
+/// Word list read from the working directory, one word per line, e.g. a copy
+/// of `/usr/share/dict/words`. Names are checked against it when present.
+const DICTIONARY_PATH: &str = "dictionary.txt";
 
-    let trolls: Vec<Character> = all_characters
-        .iter()
-        .filter(|c| c.faction == "t")
-        .cloned()
-        .collect();
+/// Common English words a generated name must not spell, whatever the dictionary
+const COMMON_WORDS: &[&str] = &[
+    "bag", "ball", "band", "bank", "bark", "bat", "bell", "bin", "bit", "bog", "bolt", "bone", "boot",
+    "brag", "brick", "bug", "bun", "cab", "cog", "crab", "crank", "drag", "drug", "fizz", "flip", "flop",
+    "frog", "fun", "gag", "gear", "glum", "grab", "grim", "grip", "grub", "grump", "gum", "gun", "hat",
+    "hug", "jam", "jug", "kin", "knob", "lag", "log", "mad", "mob", "mud", "mug", "nag", "nip", "nut",
+    "pig", "pin", "pip", "pop", "pot", "pug", "rag", "rat", "rib", "rock", "rot", "rug", "sag", "slug",
+    "smog", "snag", "snarl", "snug", "spark", "spring", "stab", "stub", "tag", "thug", "tin", "top",
+    "tug", "twig", "wig", "wink", "zip",
+];
+
+/// Lowercase words in the dictionary for this run, read on first use
+fn dictionary() -> &'static HashSet<String> {
+    static DICTIONARY: OnceLock<HashSet<String>> = OnceLock::new();
+    DICTIONARY.get_or_init(|| match fs::read_to_string(DICTIONARY_PATH) {
+        Ok(words) => words.lines().map(|w| w.trim().to_lowercase()).filter(|w| !w.is_empty()).collect(),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => HashSet::new(),
+        Err(e) => {
+            eprintln!("⚠️ Could not read dictionary {}: {}", DICTIONARY_PATH, e);
+            HashSet::new()
+        }
+    })
+}
+
+/// Character-level Markov chain over the names of one faction
+#[derive(Debug, Default)]
+struct NameModel {
+    order: usize,
+    /// Preceding `order` characters -> how often each character follows them
+    transitions: BTreeMap<String, BTreeMap<char, u32>>,
+}
+
+impl NameModel {
+    fn train(names: &[&str], order: usize) -> Self {
+        let mut model = NameModel { order, transitions: BTreeMap::new() };
+        for name in names {
+            let padded: Vec<char> = std::iter::repeat_n(START, order)
+                .chain(name.to_lowercase().chars())
+                .chain(std::iter::once(END))
+                .collect();
+            for window in padded.windows(order + 1) {
+                let context: String = window[..order].iter().collect();
+                *model.transitions.entry(context).or_default().entry(window[order]).or_insert(0) += 1;
+            }
+        }
+        model
+    }
+
+    /// Walk the chain once; `None` if it runs past `max_len` or hits an unseen context
+    fn sample(&self, rng: &mut StdRng, max_len: usize) -> Option<String> {
+        let mut context: Vec<char> = vec![START; self.order];
+        let mut name = String::new();
+        loop {
+            let key: String = context.iter().collect();
+            let next = self.transitions.get(&key)?;
+            let total: u32 = next.values().sum();
+            let mut roll = rng.random_range(0..total);
+            let c = *next
+                .iter()
+                .find(|(_, count)| {
+                    if roll < **count {
+                        return true;
+                    }
+                    roll -= **count;
+                    false
+                })?
+                .0;
+            if c == END {
+                return Some(name);
+            }
+            name.push(c);
+            if name.chars().count() > max_len {
+                return None;
+            }
+            context.remove(0);
+            context.push(c);
+        }
+    }
+}
+
+/// Seeded generator of new names in the style of each faction's names in the pool.
+///
+/// Every name it returns is unique against those it has returned or been told
+/// about, and never spells a blocked real word.
+pub struct NameGenerator {
+    params: NameParams,
+    rng: StdRng,
+    /// Faction id -> its names in the pool
+    pool: BTreeMap<String, Vec<String>>,
+    models: BTreeMap<String, NameModel>,
+    /// Lowercase names already in use
+    taken: HashSet<String>,
+    blocked: HashSet<String>,
+}
+
+impl NameGenerator {
+    pub fn new(pool: &[Character], params: &NameParams, seed: u64) -> Self {
+        let mut by_faction: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for c in pool {
+            by_faction.entry(c.faction.clone()).or_default().push(c.name.clone());
+        }
+        let models = by_faction
+            .iter()
+            .map(|(faction, names)| {
+                let names: Vec<&str> = names.iter().map(String::as_str).collect();
+                (faction.clone(), NameModel::train(&names, params.order.max(1)))
+            })
+            .collect();
+
+        let mut blocked: HashSet<String> = params.blocked_words.iter().map(|w| w.to_lowercase()).collect();
+        if params.avoid_real_words {
+            blocked.extend(COMMON_WORDS.iter().map(|w| w.to_string()));
+            blocked.extend(dictionary().iter().cloned());
+        }
+
+        NameGenerator {
+            params: params.clone(),
+            rng: StdRng::seed_from_u64(seed),
+            pool: by_faction,
+            models,
+            taken: HashSet::new(),
+            blocked,
+        }
+    }
+
+    /// Mark a name as in use so it is never generated
+    pub fn reserve(&mut self, name: &str) {
+        self.taken.insert(name.to_lowercase());
+    }
+
+    /// Reserve the names of everyone taking part in `events`
+    pub fn reserve_events(&mut self, events: &[Event]) {
+        for character in events.iter().flat_map(|e| &e.characters) {
+            self.reserve(&character.name);
+        }
+    }
+
+    fn is_free(&self, name: &str) -> bool {
+        let key = name.to_lowercase();
+        !self.taken.contains(&key) && !self.blocked.contains(&key)
+    }
+
+    /// A new, unused name for a member of `faction`, reserving it.
+    /// Falls back to an unused name from the pool, and `None` once both run dry.
+    pub fn generate(&mut self, faction: &str) -> Option<String> {
+        let known: HashSet<String> = self.pool.get(faction)?.iter().map(|n| n.to_lowercase()).collect();
+
+        // Step 1: Walk the chain until it produces something new
+        if let Some(model) = self.models.get(faction) {
+            for _ in 0..MAX_ATTEMPTS {
+                let Some(name) = model.sample(&mut self.rng, self.params.max_length) else {
+                    continue;
+                };
+                if name.chars().count() < self.params.min_length || known.contains(&name) || !self.is_free(&name) {
+                    continue;
+                }
+                let name = capitalise(&name);
+                self.reserve(&name);
+                return Some(name);
+            }
+        }
 
-    let centaurs: Vec<Character> = all_characters
+        // Step 2: Short or repetitive training sets can be exhausted; reuse the pool
+        let unused: Vec<String> = self.pool[faction].iter().filter(|n| self.is_free(n)).cloned().collect();
+        let name = unused.choose(&mut self.rng)?.clone();
+        self.reserve(&name);
+        Some(name)
+    }
+}
+
+fn capitalise(name: &str) -> String {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// The role of each roster slot: every quota filled first, in role order,
+/// then the rest shared between `FILL_ROLES`
+pub fn roster_roles(params: &RosterParams) -> Result<Vec<Role>, String> {
+    if params.roster_size == 0 {
+        return Err("Rosters need at least one character".into());
+    }
+    // Succession and the living-ruler rule start from each faction's ruler
+    if params.role_quotas.get(&Role::Ruler).copied().unwrap_or(0) != 1 {
+        return Err("Role quotas must give each faction exactly one ruler".into());
+    }
+    let reserved: usize = params.role_quotas.values().sum();
+    if reserved > params.roster_size {
+        return Err(format!(
+            "Role quotas need {} characters but rosters only have {}",
+            reserved, params.roster_size
+        ));
+    }
+
+    let mut roles: Vec<Role> = params
+        .role_quotas
         .iter()
-        .filter(|c| c.faction == "c")
-        .cloned()
+        .flat_map(|(&role, &count)| std::iter::repeat_n(role, count))
         .collect();
+    roles.extend(FILL_ROLES.iter().cycle().take(params.roster_size - reserved));
+    Ok(roles)
+}
 
+/// Pick a cast of characters for the first `faction_count` factions, with
+/// names unlike anyone already taking part in `events`
+pub fn gen_characters(
+    faction_count: usize,
+    params: &RosterParams,
+    events: &[Event],
+    seed: u64,
+) -> Result<Vec<Character>, String> {
+    let roles = roster_roles(params)?;
+    names().check_roster(faction_count, params.roster_size)?;
+    let mut generator = NameGenerator::new(names().characters(), &params.names, seed);
+    generator.reserve_events(events);
 
-    let mut rng = rand::thread_rng();
+    let mut rng = StdRng::seed_from_u64(seed);
 
-    // Randomly select 4 of each faction
+    // A full roster of each faction, named in its style
     let mut selected = Vec::new();
-    selected.extend(gnomes.choose_multiple(&mut rng, 4).cloned());
-    selected.extend(trolls.choose_multiple(&mut rng, 4).cloned());
-    selected.extend(centaurs.choose_multiple(&mut rng, 4).cloned());
+    for faction in factions().iter().take(faction_count) {
+        for &role in &roles {
+            if let Some(name) = generator.generate(&faction.id) {
+                let traits = TRAITS.sample(&mut rng, 2).map(|t| t.to_string()).collect();
+                selected.push(Character {
+                    id: fresh_id("ch", &mut rng),
+                    name,
+                    faction: faction.id.clone(),
+                    role: Some(role),
+                    traits,
+                    ..Default::default()
+                });
+            }
+        }
+    }
+
+    Ok(selected)
+}
+
+/// Archetypes each role would rather live in, best first
+fn preferred_homes(role: Option<Role>) -> &'static [Archetype] {
+    match role {
+        Some(Role::Ruler) | Some(Role::Heir) => &[Archetype::Castle, Archetype::Tower],
+        Some(Role::Knight) => &[Archetype::Tower, Archetype::Castle],
+        Some(Role::Priest) => &[Archetype::Cathedral],
+        Some(Role::Merchant) => &[Archetype::Market, Archetype::Tavern],
+        None => &[],
+    }
+}
+
+/// Give every character without a home one of the places their faction holds,
+/// preferring places that suit their role
+pub fn assign_homes(characters: &mut [Character], ownership: &Ownership, seed: u64) {
+    let mut rng = StdRng::seed_from_u64(seed);
+    for character in characters.iter_mut().filter(|c| c.home.is_none()) {
+        let mut held: Vec<&Place> = ownership
+            .iter()
+            .filter(|(_, faction)| **faction == character.faction)
+            .map(|(place, _)| place)
+            .collect();
+        // Ownership is a HashMap; sort so the same seed gives the same homes
+        held.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let suited: Vec<&Place> = preferred_homes(character.role)
+            .iter()
+            .map(|archetype| held.iter().filter(|p| p.archetype == *archetype).copied().collect::<Vec<_>>())
+            .find(|places| !places.is_empty())
+            .unwrap_or(held);
+        character.home = suited.choose(&mut rng).map(|p| p.id.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_seed_same_names() {
+        let pool = vec![
+            Character { name: "Fizzlewick".to_string(), faction: "g".to_string(), ..Default::default() },
+            Character { name: "Tinkerbolt".to_string(), faction: "g".to_string(), ..Default::default() },
+            Character { name: "Whistlecrank".to_string(), faction: "g".to_string(), ..Default::default() },
+            Character { name: "Bibblepop".to_string(), faction: "g".to_string(), ..Default::default() },
+            Character { name: "Nimblegear".to_string(), faction: "g".to_string(), ..Default::default() },
+            Character { name: "Twiddlewrench".to_string(), faction: "g".to_string(), ..Default::default() },
+            Character { name: "Puddlewink".to_string(), faction: "g".to_string(), ..Default::default() },
+            Character { name: "Grak".to_string(), faction: "t".to_string(), ..Default::default() },
+            Character { name: "Brug".to_string(), faction: "t".to_string(), ..Default::default() },
+            Character { name: "Thok".to_string(), faction: "t".to_string(), ..Default::default() },
+            Character { name: "Krag".to_string(), faction: "t".to_string(), ..Default::default() },
+            Character { name: "Grum".to_string(), faction: "t".to_string(), ..Default::default() },
+            Character { name: "Drok".to_string(), faction: "t".to_string(), ..Default::default() },
+        ];
+        let params = NameParams::default();
+        let mut a = NameGenerator::new(&pool, &params, 11);
+        let mut b = NameGenerator::new(&pool, &params, 11);
+        for _ in 0..5 {
+            assert_eq!(a.generate("g"), b.generate("g"));
+        }
+    }
+
+    #[test]
+    fn test_names_unique_and_not_blocked() {
+        let pool = vec![
+            Character { name: "Fizzlewick".to_string(), faction: "g".to_string(), ..Default::default() },
+            Character { name: "Tinkerbolt".to_string(), faction: "g".to_string(), ..Default::default() },
+            Character { name: "Whistlecrank".to_string(), faction: "g".to_string(), ..Default::default() },
+            Character { name: "Bibblepop".to_string(), faction: "g".to_string(), ..Default::default() },
+            Character { name: "Nimblegear".to_string(), faction: "g".to_string(), ..Default::default() },
+            Character { name: "Twiddlewrench".to_string(), faction: "g".to_string(), ..Default::default() },
+            Character { name: "Puddlewink".to_string(), faction: "g".to_string(), ..Default::default() },
+            Character { name: "Grak".to_string(), faction: "t".to_string(), ..Default::default() },
+            Character { name: "Brug".to_string(), faction: "t".to_string(), ..Default::default() },
+            Character { name: "Thok".to_string(), faction: "t".to_string(), ..Default::default() },
+            Character { name: "Krag".to_string(), faction: "t".to_string(), ..Default::default() },
+            Character { name: "Grum".to_string(), faction: "t".to_string(), ..Default::default() },
+            Character { name: "Drok".to_string(), faction: "t".to_string(), ..Default::default() },
+        ];
+        let params = NameParams { blocked_words: vec!["Grak".to_string()], ..NameParams::default() };
+        let mut names = NameGenerator::new(&pool, &params, 3);
+        names.reserve("Brug");
+
+        let mut seen = HashSet::new();
+        for _ in 0..6 {
+            let name = names.generate("t").unwrap();
+            assert!(seen.insert(name.to_lowercase()), "{} generated twice", name);
+            assert!(name != "Grak" && name != "Brug");
+            assert!(!COMMON_WORDS.contains(&name.to_lowercase().as_str()));
+        }
+        assert_eq!(names.generate("x"), None);
+    }
+
+    #[test]
+    fn test_roster_ids_are_unique_and_seeded() {
+        let a = gen_characters(2, &RosterParams::default(), &[], 9).unwrap();
+        let b = gen_characters(2, &RosterParams::default(), &[], 9).unwrap();
+        let ids: HashSet<&str> = a.iter().map(|c| c.id.as_str()).collect();
+        assert_eq!(ids.len(), a.len());
+        assert_eq!(a.iter().map(|c| &c.id).collect::<Vec<_>>(), b.iter().map(|c| &c.id).collect::<Vec<_>>());
+        // Ids don't come from names, so a namesake born later can't take one
+        assert!(a.iter().all(|c| c.id != crate::utils::ids::character_id(&c.name, &c.faction)));
+    }
+
+    #[test]
+    fn test_roster_roles_follow_quotas() {
+        let default = roster_roles(&RosterParams::default()).unwrap();
+        assert_eq!(default, vec![Role::Ruler, Role::Heir, Role::Knight, Role::Priest]);
+
+        let params = RosterParams {
+            roster_size: 6,
+            role_quotas: BTreeMap::from([(Role::Ruler, 1), (Role::Priest, 2)]),
+            ..RosterParams::default()
+        };
+        let roles = roster_roles(&params).unwrap();
+        assert_eq!(roles, vec![Role::Ruler, Role::Priest, Role::Priest, Role::Knight, Role::Merchant, Role::Priest]);
+
+        let too_many = RosterParams { roster_size: 2, ..RosterParams::default() };
+        assert!(roster_roles(&too_many).unwrap_err().contains("only have 2"));
 
-    selected
+        // Quotas replace the defaults, so leaving out the ruler is an error
+        let no_ruler = RosterParams { role_quotas: BTreeMap::from([(Role::Priest, 2)]), ..RosterParams::default() };
+        assert!(roster_roles(&no_ruler).unwrap_err().contains("exactly one ruler"));
+        let two_rulers = RosterParams { role_quotas: BTreeMap::from([(Role::Ruler, 2)]), ..RosterParams::default() };
+        assert!(roster_roles(&two_rulers).is_err());
+    }
 }
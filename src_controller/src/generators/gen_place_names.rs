@@ -0,0 +1,208 @@
+use std::collections::HashSet;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rand::prelude::IndexedRandom;
+
+use crate::types::{Archetype, Faction, Map, Ownership};
+use crate::utils::factions::factions;
+use crate::utils::ids::fnv1a;
+use crate::utils::prompt::get_medieval_place_name;
+
+/// Modern words and their period replacements; one is picked per seed
+const SUBSTITUTIONS: &[(&str, &[&str])] = &[
+    ("national", &["Royal", "King's"]),
+    ("international", &["Great"]),
+    ("centre", &["Hall", "Yard"]),
+    ("center", &["Hall", "Yard"]),
+    ("museum", &["Reliquary", "Hall of Relics"]),
+    ("gallery", &["Gallery", "Long Hall"]),
+    ("art", &["Limner's", "Tapestry"]),
+    ("arts", &["Crafts", "Guildcraft"]),
+    ("ice", &["Frost", "Rime"]),
+    ("park", &["Green", "Common"]),
+    ("gardens", &["Herb Gardens", "Orchards"]),
+    ("garden", &["Herb Garden", "Orchard"]),
+    ("station", &["Waystation", "Coaching House"]),
+    ("theatre", &["Playhouse", "Mummers' Hall"]),
+    ("theater", &["Playhouse", "Mummers' Hall"]),
+    ("cinema", &["Lantern Hall"]),
+    ("stadium", &["Tiltyard", "Lists"]),
+    ("arena", &["Lists", "Tiltyard"]),
+    ("university", &["Scriptorium", "Collegium"]),
+    ("college", &["Collegium"]),
+    ("library", &["Scriptorium"]),
+    ("hospital", &["Infirmary", "Almshouse"]),
+    ("church", &["Kirk", "Chapel"]),
+    ("cathedral", &["Minster"]),
+    ("square", &["Market Cross", "Green"]),
+    ("street", &["Lane", "Way"]),
+    ("road", &["Way", "Track"]),
+    ("bridge", &["Bridge", "Crossing"]),
+    ("house", &["Manor", "House"]),
+    ("hotel", &["Inn", "Hostelry"]),
+    ("bar", &["Alehouse"]),
+    ("pub", &["Alehouse", "Tavern"]),
+    ("shopping", &["Merchants'"]),
+    ("mall", &["Bazaar"]),
+    ("zoo", &["Menagerie"]),
+    ("aquarium", &["Fishponds"]),
+    ("memorial", &["Cenotaph", "Cairn"]),
+    ("monument", &["Cairn", "Standing Stone"]),
+    ("great", &["Great", "Mighty"]),
+    ("britain", &["Albion"]),
+    ("&", &["and"]),
+];
+
+/// Modern filler dropped outright
+const DROPPED: &[&str] = &["ltd", "plc", "uk", "the", "ss", "hms"];
+
+/// Archetype word appended when the name doesn't already say what it is
+fn archetype_template(archetype: Archetype) -> &'static [&'static str] {
+    match archetype {
+        Archetype::Castle => &["{} Keep", "Castle {}"],
+        Archetype::Cathedral => &["Abbey of {}", "{} Minster"],
+        Archetype::Market => &["{} Market", "{} Fair"],
+        Archetype::Tavern => &["The {} Inn", "{} Alehouse"],
+        Archetype::Tower => &["{} Tower", "Tower of {}"],
+        Archetype::Village => &["{}"],
+    }
+}
+
+/// Words that already mark a name as period-appropriate, so no archetype word is added
+const ARCHETYPE_WORDS: &[&str] = &[
+    "keep", "castle", "palace", "fort", "hall", "manor", "abbey", "minster", "kirk", "chapel",
+    "priory", "market", "fair", "bazaar", "inn", "alehouse", "tavern", "hostelry", "tower",
+    "reliquary", "scriptorium", "collegium",
+];
+
+/// Turn a modern place name into a period-appropriate one.
+///
+/// Rule based and fully deterministic: the same name, archetype, faction and
+/// seed always produce the same result.
+pub fn medievalise_name(name: &str, archetype: Archetype, faction: Option<&Faction>, seed: u64) -> String {
+    let mut rng = StdRng::seed_from_u64(seed ^ fnv1a(name));
+
+    // Step 1: Lexical substitution, word by word
+    let words: Vec<String> = name
+        .split_whitespace()
+        .filter_map(|word| {
+            let key = word.trim_matches(|c: char| !c.is_alphanumeric() && c != '&').to_lowercase();
+            if DROPPED.contains(&key.as_str()) {
+                return None;
+            }
+            let replaced = SUBSTITUTIONS
+                .iter()
+                .find(|(modern, _)| *modern == key)
+                .and_then(|(_, options)| options.choose(&mut rng))
+                .map(|s| s.to_string())
+                .unwrap_or_else(|| word.to_string());
+            Some(replaced)
+        })
+        .collect();
+    let mut result = if words.is_empty() { name.to_string() } else { words.join(" ") };
+
+    // Step 2: Make the archetype explicit
+    let lower = result.to_lowercase();
+    if !ARCHETYPE_WORDS.iter().any(|w| lower.split_whitespace().any(|x| x == *w)) {
+        let template = archetype_template(archetype).choose(&mut rng).unwrap();
+        result = template.replace("{}", &result);
+    }
+
+    // Step 3: Faction flavour, applied to roughly half the names a faction owns
+    if let Some(faction) = faction && rng.random_bool(0.5)
+        && let Some(template) = faction.naming.templates.choose(&mut rng)
+    {
+        result = template.replace("{}", &result);
+    }
+
+    result
+}
+
+/// Rename every place in the map, keeping the modern name in `info.original_name`.
+///
+/// Places that already have an `original_name` (e.g. loaded from an edited
+/// GeoJSON) keep their current name. Names are kept unique so players can
+/// tell places apart. When
+/// `use_llm` is set each rule-based draft is polished by the LLM, falling back
+/// to the draft if the request fails. Returns the ownership re-keyed to the
+/// renamed places.
+pub async fn medievalise_map(map: &mut Map, ownership: Ownership, seed: u64, use_llm: bool) -> Ownership {
+    let mut taken: HashSet<String> = HashSet::new();
+    let mut renamed = Ownership::new();
+
+    // Names that are already medieval are reserved first
+    for place in map.locations.iter().filter(|p| p.info.original_name.is_some()) {
+        taken.insert(place.name.clone());
+    }
+
+    for place in map.locations.iter_mut() {
+        let faction = ownership.get(place).cloned();
+        let style = faction.as_deref().and_then(|id| factions().get(id));
+        if place.info.original_name.is_some() {
+            if let Some(faction) = faction {
+                renamed.insert(place.clone(), faction);
+            }
+            continue;
+        }
+        let original = place.name.clone();
+
+        let mut name = medievalise_name(&original, place.archetype, style, seed);
+        if use_llm {
+            match get_medieval_place_name(&original, &name, place.archetype, style, seed).await {
+                Ok(polished) => name = polished,
+                Err(e) => eprintln!("⚠️ LLM naming failed for {}: {}", original, e),
+            }
+        }
+
+        // Disambiguate collisions with ordinals
+        let base = name.clone();
+        let mut ordinal = 2;
+        while taken.contains(&name) {
+            name = format!("{} {}", base, roman(ordinal));
+            ordinal += 1;
+        }
+        taken.insert(name.clone());
+
+        place.info.original_name = Some(original);
+        place.name = name;
+
+        if let Some(faction) = faction {
+            renamed.insert(place.clone(), faction);
+        }
+    }
+
+    renamed
+}
+
+fn roman(n: usize) -> String {
+    const NUMERALS: &[(usize, &str)] = &[(10, "X"), (9, "IX"), (5, "V"), (4, "IV"), (1, "I")];
+    let mut n = n;
+    let mut out = String::new();
+    for &(value, numeral) in NUMERALS {
+        while n >= value {
+            out.push_str(numeral);
+            n -= value;
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deterministic_for_seed() {
+        let trolls = factions().get("t");
+        let a = medievalise_name("National Ice Centre", Archetype::Village, trolls, 42);
+        let b = medievalise_name("National Ice Centre", Archetype::Village, trolls, 42);
+        assert_eq!(a, b);
+        assert!(!a.contains("National") && !a.contains("Centre"));
+    }
+
+    #[test]
+    fn test_existing_archetype_word_not_repeated() {
+        let name = medievalise_name("Nottingham Castle", Archetype::Castle, None, 7);
+        assert_eq!(name, "Nottingham Castle");
+    }
+}
@@ -1,10 +1,89 @@
-use crate::types::{Map, Place};
+use crate::generators::gen_features::fetch_features;
+use crate::types::{Map, MapParams, Place, PlaceInfo, Region, RoadEdge, RoadNetwork};
+use crate::utils::archetypes::classify;
+use crate::utils::ids::{make_id, place_id};
+use crate::utils::projection::Projection;
+use crate::utils::roads::{curved_road, plan_highways, plan_roads, straight_road};
+use crate::utils::simplify::simplify_polyline;
 use serde::Deserialize;
 use reqwest::Client;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+/// Largest deviation (in game units) allowed when simplifying a route
+const ROUTE_TOLERANCE: f64 = 0.004;
+/// Most vertices kept per route after simplification
+const ROUTE_POINT_BUDGET: usize = 48;
+/// Most directions requests in flight at once
+const MAX_CONCURRENT_ROUTES: usize = 4;
+/// How long a single directions request may take
+const ROUTE_TIMEOUT: Duration = Duration::from_secs(10);
+/// Further attempts after a directions request fails in a way that may pass
+const ROUTE_RETRIES: u32 = 2;
+
+/// Why the maps provider couldn't give us a route
+#[derive(Debug)]
+pub enum ProviderError {
+    /// Rate limited; worth retrying after a pause
+    OverQueryLimit,
+    /// No route exists, e.g. across water
+    ZeroResults,
+    /// The key was rejected; every other request will fail the same way
+    RequestDenied(Option<String>),
+    /// Any other non-OK status
+    Status(String, Option<String>),
+    Timeout,
+    Http(reqwest::Error),
+}
+
+impl ProviderError {
+    /// Read a Google `status` field, which is `OK` on success
+    fn from_status(status: &str, message: Option<String>) -> Option<Self> {
+        match status {
+            "OK" => None,
+            "OVER_QUERY_LIMIT" => Some(ProviderError::OverQueryLimit),
+            "ZERO_RESULTS" => Some(ProviderError::ZeroResults),
+            "REQUEST_DENIED" => Some(ProviderError::RequestDenied(message)),
+            other => Some(ProviderError::Status(other.to_string(), message)),
+        }
+    }
+
+    fn is_retryable(&self) -> bool {
+        matches!(self, ProviderError::OverQueryLimit | ProviderError::Timeout | ProviderError::Http(_))
+    }
+}
+
+impl fmt::Display for ProviderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProviderError::OverQueryLimit => write!(f, "over query limit"),
+            ProviderError::ZeroResults => write!(f, "no route found"),
+            ProviderError::RequestDenied(message) => write!(f, "request denied: {}", message.as_deref().unwrap_or("no reason given")),
+            ProviderError::Status(status, message) => write!(f, "{}: {}", status, message.as_deref().unwrap_or("no reason given")),
+            ProviderError::Timeout => write!(f, "timed out after {:?}", ROUTE_TIMEOUT),
+            ProviderError::Http(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for ProviderError {}
+
+impl From<reqwest::Error> for ProviderError {
+    fn from(e: reqwest::Error) -> Self {
+        ProviderError::Http(e)
+    }
+}
 
 #[derive(Debug, Deserialize)]
 struct GeocodeResponse {
     results: Vec<GeocodeResult>,
+    #[serde(default)]
+    status: String,
+    #[serde(default)]
+    error_message: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -27,22 +106,53 @@ struct LatLng {
 #[derive(Debug, Deserialize)]
 struct PlacesResponse {
     results: Vec<PlaceResult>,
+    #[serde(default)]
+    status: String,
+    #[serde(default)]
+    error_message: Option<String>,
+    #[serde(default)]
+    next_page_token: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 struct PlaceResult {
+    #[serde(default)]
+    place_id: Option<String>,
     name: String,
     geometry: Geometry,
+    #[serde(default)]
+    types: Vec<String>,
+    #[serde(default)]
+    rating: Option<f64>,
+    #[serde(default)]
+    vicinity: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 struct DirectionsResponse {
+    #[serde(default)]
+    status: String,
+    #[serde(default)]
+    error_message: Option<String>,
     routes: Vec<DirectionsRoute>,
 }
 
 #[derive(Debug, Deserialize)]
 struct DirectionsRoute {
     overview_polyline: Polyline,
+    #[serde(default)]
+    legs: Vec<DirectionsLeg>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DirectionsLeg {
+    distance: TextValue,
+    duration: TextValue,
+}
+
+#[derive(Debug, Deserialize)]
+struct TextValue {
+    value: f64,
 }
 
 #[derive(Debug, Deserialize)]
@@ -106,144 +216,294 @@ fn haversine_distance(a: (f64, f64), b: (f64, f64)) -> f64 {
     2.0 * r * hav.sqrt().asin()
 }
 
-pub async fn fetch_map(
-    place: &str,
-    n: usize,
-    min_distance_m: f64,
-) -> Result<Map, Box<dyn std::error::Error>> {
-    let api_key = std::env::var("GOOGLE_API_KEY")?;
-    let client = Client::new();
+/// Google returns at most this many pages (of 20 results) per search
+const MAX_PLACES_PAGES: usize = 3;
+/// Largest radius the Places API accepts, in metres
+const MAX_SEARCH_RADIUS_M: f64 = 50000.0;
+
+/// Run a nearby search for one place type, adding results to `locations` until
+/// `params.count` places at least `params.min_spacing_m` apart have been found
+async fn search_places(
+    client: &Client,
+    api_key: &str,
+    center: (f64, f64),
+    place_type: &str,
+    params: &MapParams,
+    locations: &mut Vec<Place>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let base_url = format!(
+        "https://maps.googleapis.com/maps/api/place/nearbysearch/json?location={},{}&radius={}&type={}&key={}",
+        center.0,
+        center.1,
+        params.radius_m.clamp(1.0, MAX_SEARCH_RADIUS_M),
+        urlencoding::encode(place_type),
+        api_key
+    );
+
+    let mut page_token: Option<String> = None;
+    for _ in 0..MAX_PLACES_PAGES {
+        let url = match &page_token {
+            Some(token) => format!("{}&pagetoken={}", base_url, urlencoding::encode(token)),
+            None => base_url.clone(),
+        };
+
+        let mut places_res: PlacesResponse = client.get(&url).send().await?.json().await?;
+
+        // A fresh page token takes a moment to become valid; wait and retry once
+        if places_res.status == "INVALID_REQUEST" && page_token.is_some() {
+            tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+            places_res = client.get(&url).send().await?.json().await?;
+        }
+        if places_res.status == "REQUEST_DENIED" {
+            return Err(Box::new(ProviderError::RequestDenied(places_res.error_message)));
+        }
 
-    // Step 1: Geocode starting place
+        for p in places_res.results.into_iter() {
+            let info = PlaceInfo {
+                types: p.types,
+                rating: p.rating,
+                vicinity: p.vicinity,
+                original_name: None,
+            };
+            let location = (p.geometry.location.lat, p.geometry.location.lng);
+            let candidate = Place {
+                // Google's own id keeps a place's id the same across fetches
+                id: match &p.place_id {
+                    Some(google_id) => make_id("pl", google_id),
+                    None => place_id(&p.name, location),
+                },
+                archetype: classify(&p.name, &info),
+                name: p.name,
+                location,
+                info,
+            };
+
+            if locations
+                .iter()
+                .all(|existing: &Place| haversine_distance(existing.location, candidate.location) >= params.min_spacing_m)
+            {
+                locations.push(candidate);
+            }
+
+            if locations.len() >= params.count {
+                return Ok(());
+            }
+        }
+
+        match places_res.next_page_token {
+            Some(token) => {
+                page_token = Some(token);
+                // Google needs a short delay before the next page can be requested
+                tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+            }
+            None => break,
+        }
+    }
+
+    Ok(())
+}
+
+/// Make one directions request and check its status
+async fn request_route(client: &Client, url: &str) -> Result<DirectionsRoute, ProviderError> {
+    let res: DirectionsResponse = client.get(url).send().await?.json().await?;
+    if let Some(e) = ProviderError::from_status(&res.status, res.error_message) {
+        return Err(e);
+    }
+    res.routes.into_iter().next().ok_or(ProviderError::ZeroResults)
+}
+
+/// Fetch a route with a timeout on each attempt, backing off and retrying
+/// failures that may clear up
+async fn fetch_route(client: &Client, url: &str) -> Result<DirectionsRoute, ProviderError> {
+    let mut attempt = 0;
+    loop {
+        let result = tokio::time::timeout(ROUTE_TIMEOUT, request_route(client, url))
+            .await
+            .unwrap_or(Err(ProviderError::Timeout));
+        match result {
+            Err(e) if e.is_retryable() && attempt < ROUTE_RETRIES => {
+                attempt += 1;
+                tokio::time::sleep(Duration::from_millis(500 * 2u64.pow(attempt))).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Geocode a place name to its `(lat, lng)`
+async fn geocode(client: &Client, api_key: &str, place: &str) -> Result<(f64, f64), Box<dyn std::error::Error>> {
     let geo_url = format!(
         "https://maps.googleapis.com/maps/api/geocode/json?address={}&key={}",
         urlencoding::encode(place),
         api_key
     );
     let geo_res: GeocodeResponse = client.get(&geo_url).send().await?.json().await?;
+    if geo_res.status == "REQUEST_DENIED" {
+        return Err(Box::new(ProviderError::RequestDenied(geo_res.error_message)));
+    }
     let first_result = geo_res
         .results
-        .get(0)
-        .ok_or("No results found for that place")?;
-    let center = (
-        first_result.geometry.location.lat,
-        first_result.geometry.location.lng,
-    );
-
-    // Step 2: Find nearby attractions
-    let places_url = format!(
-        "https://maps.googleapis.com/maps/api/place/nearbysearch/json?location={},{}&radius=1609&type=tourist_attraction&key={}",
-        center.0, center.1, api_key
-    );
-    let places_res: PlacesResponse = client.get(&places_url).send().await?.json().await?;
+        .first()
+        .ok_or_else(|| format!("No results found for {}", place))?;
+    Ok((first_result.geometry.location.lat, first_result.geometry.location.lng))
+}
 
-    let mut locations = Vec::new();
-    for p in places_res.results.into_iter() {
-        let candidate = Place {
-            name: p.name,
-            location: (p.geometry.location.lat, p.geometry.location.lng),
-        };
+/// Build a realm from one or more cities.
+///
+/// Each city contributes up to `params.count` places, kept together as a
+/// `Region`. Roads are planned within each city, and highways join the cities
+/// into one network. Routes are fetched concurrently, and any the provider
+/// can't supply are drawn in rather than failing the map, unless the provider
+/// denies the requests outright.
+pub async fn fetch_map(
+    cities: &[String],
+    params: &MapParams,
+) -> Result<Map, Box<dyn std::error::Error>> {
+    let api_key = std::env::var("GOOGLE_API_KEY")?;
+    let client = Client::new();
 
-        if locations
-            .iter()
-            .all(|existing: &Place| haversine_distance(existing.location, candidate.location) >= min_distance_m)
-        {
-            locations.push(candidate);
+    // Step 1: Geocode each city and find places around it, following
+    // pagination until enough are spaced out
+    let mut locations: Vec<Place> = Vec::new();
+    let mut regions: Vec<Region> = Vec::new();
+    let mut centers: Vec<(f64, f64)> = Vec::new();
+    for city in cities {
+        let center = geocode(&client, &api_key, city).await?;
+
+        let mut found: Vec<Place> = Vec::new();
+        for place_type in &params.place_types {
+            if found.len() >= params.count {
+                break;
+            }
+            search_places(&client, &api_key, center, place_type, params, &mut found).await?;
         }
 
-        if locations.len() >= n {
-            break;
+        // Neighbouring cities can share places; keep whichever was found first
+        let mut region = Region { name: city.clone(), places: Vec::new() };
+        for candidate in found {
+            if locations
+                .iter()
+                .all(|existing| haversine_distance(existing.location, candidate.location) >= params.min_spacing_m)
+            {
+                region.places.push(locations.len());
+                locations.push(candidate);
+            }
         }
+        regions.push(region);
+        centers.push(center);
     }
 
     if locations.is_empty() {
         return Err("No locations found".into());
     }
 
-    // Step 3: Compute centroid & scale for normalization
-    let (sum_lat, sum_lng) = locations.iter().fold((0.0, 0.0), |acc, loc| {
-        (acc.0 + loc.location.0, acc.1 + loc.location.1)
-    });
-    let centroid = (sum_lat / locations.len() as f64, sum_lng / locations.len() as f64);
+    // Step 2: Project every city into one unit circle, north up, leaving a small margin
+    let projection = Projection::fit(
+        &locations.iter().map(|loc| loc.location).collect::<Vec<_>>(),
+        0.9,
+    );
+    let transform_point = |p: (f64, f64)| projection.forward(p);
 
-    let max_dist = locations
+    // Transform locations
+    let transformed_locations: Vec<Place> = locations
         .iter()
-        .map(|loc| {
-            let dx = loc.location.0 - centroid.0;
-            let dy = loc.location.1 - centroid.1;
-            (dx * dx + dy * dy).sqrt()
+        .map(|loc| Place {
+            location: transform_point(loc.location),
+            ..loc.clone()
         })
-        .fold(0.0, f64::max)
-        .max(1e-9);
+        .collect();
 
-    let scale = 0.9 / max_dist;
+    // Step 3: Pick which places to join, independent of the Places API result order.
+    // Streets are planned within each city, then highways link the cities.
+    let points: Vec<(f64, f64)> = transformed_locations.iter().map(|p| p.location).collect();
+    let mut pairs: Vec<(usize, usize, bool)> = Vec::new();
+    for region in &regions {
+        let local: Vec<(f64, f64)> = region.places.iter().map(|&i| points[i]).collect();
+        for (a, b) in plan_roads(&local) {
+            pairs.push((region.places[a], region.places[b], false));
+        }
+    }
+    let region_places: Vec<Vec<usize>> = regions.iter().map(|r| r.places.clone()).collect();
+    for (from, to) in plan_highways(&points, &region_places) {
+        pairs.push((from, to, true));
+    }
 
-    // Step 4: Fetch routes
-    let mut routes: Vec<Vec<(f64, f64)>> = Vec::new();
-    for i in 0..locations.len().saturating_sub(1) {
-        let origin = locations[i].location;
-        let dest = locations[i + 1].location;
+    // Step 4: Fetch a route for every planned road, a few at a time
+    let semaphore = Arc::new(Semaphore::new(MAX_CONCURRENT_ROUTES));
+    let mut tasks = JoinSet::new();
+    for (road, &(from, to, _)) in pairs.iter().enumerate() {
+        let origin = locations[from].location;
+        let dest = locations[to].location;
         let directions_url = format!(
             "https://maps.googleapis.com/maps/api/directions/json?origin={},{}&destination={},{}&mode=driving&key={}",
             origin.0, origin.1, dest.0, dest.1, api_key
         );
+        let (client, semaphore) = (client.clone(), semaphore.clone());
+        tasks.spawn(async move {
+            let _permit = semaphore.acquire().await;
+            (road, fetch_route(&client, &directions_url).await)
+        });
+    }
 
-        let dir_res: DirectionsResponse = client.get(&directions_url).send().await?.json().await?;
-        if let Some(route) = dir_res.routes.get(0) {
-            let decoded = decode_polyline(&route.overview_polyline.points);
-            routes.push(decoded);
+    let mut results = Vec::with_capacity(pairs.len());
+    while let Some(joined) = tasks.join_next().await {
+        let (road, result) = joined?;
+        // A rejected key fails every request alike, so stop rather than draw the whole map in
+        if let Err(e @ ProviderError::RequestDenied(_)) = result {
+            tasks.abort_all();
+            return Err(Box::new(e));
+        }
+        results.push((road, result));
+    }
+    // Tasks finish in any order; keep roads in the order they were planned
+    results.sort_by_key(|(road, _)| *road);
+
+    // A missing route never sinks the map: it is drawn in instead
+    let mut edges: Vec<RoadEdge> = Vec::new();
+    for (&(from, to, highway), (_, result)) in pairs.iter().zip(results) {
+        match result {
+            Ok(route) => {
+                let decoded = decode_polyline(&route.overview_polyline.points);
+                let transformed: Vec<(f64, f64)> = decoded.into_iter().map(transform_point).collect();
+
+                edges.push(RoadEdge {
+                    from,
+                    to,
+                    length_m: route.legs.iter().map(|l| l.distance.value).sum(),
+                    travel_time_s: route.legs.iter().map(|l| l.duration.value).sum(),
+                    highway,
+                    path: simplify_polyline(&transformed, ROUTE_TOLERANCE, ROUTE_POINT_BUDGET),
+                });
+            }
+            Err(e) => {
+                eprintln!(
+                    "⚠️ No route from {} to {} ({}); drawing one in",
+                    locations[from].name, locations[to].name, e
+                );
+                // Highways run straight between cities; streets wander, bowing alternately
+                let edge = if highway {
+                    straight_road(&transformed_locations, from, to, Some(&projection))
+                } else {
+                    let side = if (from + to) % 2 == 0 { 1.0 } else { -1.0 };
+                    curved_road(&transformed_locations, from, to, Some(&projection), side)
+                };
+                edges.push(RoadEdge { highway, ..edge });
+            }
         }
     }
 
-    // Step 5: Unified transform function
-    let transform_point = |(lat, lng): (f64, f64)| {
-        let x = (lat - centroid.0) * scale;
-        let y = (lng - centroid.1) * scale;
-        let (x, y) = (y, -x); // rotate 90° CCW
-        (x, -y)             // mirror vertically
-    };
-
-    // Transform locations
-    let transformed_locations: Vec<Place> = locations
-        .into_iter()
-        .map(|loc| Place {
-            name: loc.name,
-            location: transform_point(loc.location),
-        })
-        .collect();
-
-    // Transform routes
-    let transformed_routes: Vec<Vec<(f64, f64)>> = routes
-        .into_iter()
-        .map(|route| {
-            let transformed: Vec<(f64, f64)> = route.into_iter().map(transform_point).collect();
-            interpolate_points(&transformed, 4)
-        })
-        .collect();
+    // Step 5: Terrain layers are optional, so a failed fetch just leaves them empty
+    let areas: Vec<((f64, f64), f64)> = centers.iter().map(|&c| (c, params.radius_m)).collect();
+    let features = fetch_features(&client, &projection, &areas, 1.0).await.unwrap_or_else(|e| {
+        eprintln!("⚠️ Could not fetch terrain features: {}", e);
+        Vec::new()
+    });
 
     Ok(Map {
         locations: transformed_locations,
-        routes: transformed_routes,
+        roads: RoadNetwork { edges },
+        projection: Some(projection),
+        features,
+        regions,
     })
 }
-
-
-// Helper function to downsample a polyline
-fn interpolate_points(points: &[(f64, f64)], n: usize) -> Vec<(f64, f64)> {
-    if points.is_empty() {
-        return Vec::new();
-    }
-
-    let mut result = Vec::new();
-    result.push(points[0]); // always include start point
-
-    for i in (1..points.len() - 1).step_by(n) {
-        result.push(points[i]);
-    }
-
-    if points.len() > 1 {
-        result.push(points[points.len() - 1]); // always include end point
-    }
-
-    result
-}
@@ -1,3 +1,5 @@
+pub mod gen_features;
 pub mod gen_names;
+pub mod gen_place_names;
 pub mod gen_places;
 pub mod gen_events;
\ No newline at end of file
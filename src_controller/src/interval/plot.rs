@@ -5,7 +5,7 @@ use std::collections::HashMap;
 
 use crate::types::Event;
 
-/// Add a new constraint (a must happen before b), validate feasibility,
+/// Add a new constraint (event id a must happen before event id b), validate feasibility,
 /// compute normalized intervals for all events, update their start/end times,
 /// and save a timeline visualization as a PNG.
 ///
@@ -18,7 +18,7 @@ pub fn add_constraint_and_get_interval(
     let (a, b) = new_constraint;
 
     // --- Update "before" list for event `a` ----------------------------
-    if let Some(idx) = existing_events.iter().position(|e| e.name == a) {
+    if let Some(idx) = existing_events.iter().position(|e| e.id == a) {
         let ev = &mut existing_events[idx];
         if !ev.before.contains(&b.to_string()) {
             ev.before.push(b.to_string());
@@ -30,8 +30,8 @@ pub fn add_constraint_and_get_interval(
     let mut nodes: HashMap<String, NodeIndex> = HashMap::new();
 
     for event in &existing_events {
-        let a_idx = *nodes.entry(event.name.clone())
-            .or_insert_with(|| graph.add_node(event.name.clone()));
+        let a_idx = *nodes.entry(event.id.clone())
+            .or_insert_with(|| graph.add_node(event.id.clone()));
         for b_name in &event.before {
             let b_idx = *nodes.entry(b_name.clone())
                 .or_insert_with(|| graph.add_node(b_name.clone()));
@@ -102,7 +102,7 @@ pub fn add_constraint_and_get_interval(
 
     // --- Update event intervals ---------------------------------------
     for event in &mut existing_events {
-        if let Some((s, e)) = segments.get(&event.name) {
+        if let Some((s, e)) = segments.get(&event.id) {
             event.start = *s;
             event.end = *e;
         }
@@ -113,7 +113,7 @@ pub fn add_constraint_and_get_interval(
     let mut new_constraint_track: f32 = -1.0;
 
     for event in &mut existing_events {
-        let (start, end) = segments[&event.name];
+        let (start, end) = segments[&event.id];
         let mut assigned = false;
 
         for (track_idx, last_end) in tracks.iter_mut().enumerate() {
@@ -132,7 +132,7 @@ pub fn add_constraint_and_get_interval(
             event.track = (tracks.len() - 1) as f32;
         }
 
-        if event.name == a || event.name == b {
+        if event.id == a || event.id == b {
             new_constraint_track = new_constraint_track.max(event.track);
         }
     }
@@ -185,8 +185,8 @@ mod tests {
     #[test]
     fn test_add_constraint_simple() {
         let mut events = vec![
-            Event { name: "A".to_string(), description: "".to_string(), before: vec![], start: 0.0, end: 0.0, _type: "".to_string(), characters: vec![], effects: vec![], track: 0.0 },
-            Event { name: "B".to_string(), description: "".to_string(), before: vec![], start: 0.0, end: 0.0, _type: "".to_string(), characters: vec![], effects: vec![], track: 0.0 },
+            Event { id: "A".to_string(), name: "A".to_string(), description: "".to_string(), before: vec![], start: 0.0, end: 0.0, _type: "".to_string(), characters: vec![], effects: vec![], requires: vec![], place: None, track: 0.0 },
+            Event { id: "B".to_string(), name: "B".to_string(), description: "".to_string(), before: vec![], start: 0.0, end: 0.0, _type: "".to_string(), characters: vec![], effects: vec![], requires: vec![], place: None, track: 0.0 },
         ];
 
         let dir = tempdir().unwrap();
@@ -216,8 +216,8 @@ mod tests {
     #[test]
     fn test_cycle_detection() {
         let events = vec![
-            Event { name: "X".to_string(), description: "".to_string(), before: vec!["Y".to_string()], start: 0.0, end: 0.0, _type: "".to_string(), characters: vec![], effects: vec![], track: 0.0 },
-            Event { name: "Y".to_string(), description: "".to_string(), before: vec![], start: 0.0, end: 0.0, _type: "".to_string(), characters: vec![], effects: vec![], track: 0.0 },
+            Event { id: "X".to_string(), name: "X".to_string(), description: "".to_string(), before: vec!["Y".to_string()], start: 0.0, end: 0.0, _type: "".to_string(), characters: vec![], effects: vec![], requires: vec![], place: None, track: 0.0 },
+            Event { id: "Y".to_string(), name: "Y".to_string(), description: "".to_string(), before: vec![], start: 0.0, end: 0.0, _type: "".to_string(), characters: vec![], effects: vec![], requires: vec![], place: None, track: 0.0 },
         ];
 
         let dir = tempdir().unwrap();
@@ -232,9 +232,9 @@ mod tests {
     #[test]
     fn test_no_cycle_multiple_events() {
         let mut events = vec![
-            Event { name: "A".to_string(), description: "".to_string(), before: vec!["B".to_string()], start: 0.0, end: 0.0, _type: "".to_string(), characters: vec![], effects: vec![], track: 0.0 },
-            Event { name: "B".to_string(), description: "".to_string(), before: vec!["C".to_string()], start: 0.0, end: 0.0, _type: "".to_string(), characters: vec![], effects: vec![], track: 0.0 },
-            Event { name: "C".to_string(), description: "".to_string(), before: vec![], start: 0.0, end: 0.0, _type: "".to_string(), characters: vec![], effects: vec![], track: 0.0 },
+            Event { id: "A".to_string(), name: "A".to_string(), description: "".to_string(), before: vec!["B".to_string()], start: 0.0, end: 0.0, _type: "".to_string(), characters: vec![], effects: vec![], requires: vec![], place: None, track: 0.0 },
+            Event { id: "B".to_string(), name: "B".to_string(), description: "".to_string(), before: vec!["C".to_string()], start: 0.0, end: 0.0, _type: "".to_string(), characters: vec![], effects: vec![], requires: vec![], place: None, track: 0.0 },
+            Event { id: "C".to_string(), name: "C".to_string(), description: "".to_string(), before: vec![], start: 0.0, end: 0.0, _type: "".to_string(), characters: vec![], effects: vec![], requires: vec![], place: None, track: 0.0 },
         ];
 
         let dir = tempdir().unwrap();
@@ -1,9 +1,23 @@
+use std::collections::HashMap;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::TcpStream;
+use serde::de::DeserializeOwned;
 use serde_json::{json, Value};
 use crate::endpoints::init_map;
 use crate::generators::gen_events::gen_event;
-use crate::types::{Character, Event};
+use crate::generators::gen_names::NameGenerator;
+use crate::solver::effects::{Naming, semantics};
+use crate::solver::solve::possible_owners;
+use crate::utils::dynasty::current_rulers;
+use crate::utils::ids::{fresh_id, upgrade_legacy};
+use crate::utils::names::names;
+use crate::types::{Archetype, Character, ClusterParams, Event, MapParams, NameParams, RosterParams, TimelineRules};
+
+/// Read a request's parameters, naming the ones that don't fit rather than
+/// quietly falling back to defaults
+fn params<T: DeserializeOwned>(request: &Value, what: &str) -> Result<T, String> {
+    serde_json::from_value(request.clone()).map_err(|e| format!("Invalid {}: {}", what, e))
+}
 
 pub async fn handle_client(mut stream: TcpStream) {
     let mut buffer = [0u8; 512];
@@ -36,12 +50,45 @@ pub async fn handle_client(mut stream: TcpStream) {
 
                                     // Handle message
                                     if let Some(init_map_obj) = parsed_json.get("INIT_MAP") {
-                                        let name = init_map_obj
-                                            .get("loc_str")
-                                            .and_then(|v| v.as_str())
-                                            .unwrap_or("default");
+                                        // A realm spans "cities" when given, otherwise the single "loc_str"
+                                        let cities: Vec<String> = init_map_obj
+                                            .get("cities")
+                                            .and_then(|v| serde_json::from_value(v.clone()).ok())
+                                            .filter(|c: &Vec<String>| !c.is_empty())
+                                            .unwrap_or_else(|| vec![init_map_obj
+                                                .get("loc_str")
+                                                .and_then(|v| v.as_str())
+                                                .unwrap_or("default")
+                                                .to_string()]);
+
+                                        let seed = init_map_obj
+                                            .get("seed")
+                                            .and_then(|v| v.as_u64())
+                                            .unwrap_or_else(rand::random);
+
+                                        let llm_names = init_map_obj
+                                            .get("llm_names")
+                                            .and_then(|v| v.as_bool())
+                                            .unwrap_or(false);
+
+                                        // Load the designers' edited map.geojson instead of fetching a fresh map
+                                        let edited_map = init_map_obj
+                                            .get("edited_map")
+                                            .and_then(|v| v.as_bool())
+                                            .unwrap_or(false);
 
-                                        let response_json = init_map(name.to_string(), true).await;
+                                        let parsed = params::<MapParams>(init_map_obj, "map parameters").and_then(|p| {
+                                            let cluster: ClusterParams = params(init_map_obj, "cluster parameters")?;
+                                            let roster: RosterParams = params(init_map_obj, "roster parameters")?;
+                                            Ok((p, cluster, roster))
+                                        });
+
+                                        let response_json = match parsed {
+                                            Ok((params, cluster_params, roster_params)) => {
+                                                init_map(cities, !edited_map, params, cluster_params, roster_params, seed, llm_names).await
+                                            }
+                                            Err(e) => json!({ "INIT_MAP": { "error": e } }).to_string(),
+                                        };
 
                                         if let Err(e) = stream.write_all(response_json.as_bytes()).await {
                                             eprintln!("Failed to send INIT_MAP response: {}", e);
@@ -55,24 +102,73 @@ pub async fn handle_client(mut stream: TcpStream) {
                                         println!("Events: {:?}", gen_events_obj
                                             .get("events"));
 
-                                        let events: Vec<Event> = gen_events_obj
+                                        let mut events: Vec<Event> = gen_events_obj
                                             .get("events")
                                             .and_then(|v| serde_json::from_value(v.clone()).ok())
                                             .unwrap_or_else(|| vec![]);
 
 
-                                        let characters: Vec<Character> = gen_events_obj
+                                        let mut characters: Vec<Character> = gen_events_obj
                                             .get("characters")
                                             .and_then(|v| serde_json::from_value(v.clone()).ok())
                                             .unwrap_or_else(|| vec![]);
 
+                                        // Older clients key everything on names
+                                        upgrade_legacy(&mut events, &mut characters);
+
+                                        // Starting ownership, relationships and the living ruler rule
+                                        let rules: TimelineRules = match params(gen_events_obj, "timeline rules") {
+                                            Ok(rules) => rules,
+                                            Err(e) => {
+                                                let response = json!({ "GEN_EVENTS": { "error": e } }).to_string();
+                                                if let Err(e) = stream.write_all(response.as_bytes()).await {
+                                                    eprintln!("Failed to send GEN_EVENTS response: {}", e);
+                                                    break;
+                                                }
+                                                continue;
+                                            }
+                                        };
+                                        // Place id -> display name
+                                        let place_names: HashMap<String, String> = gen_events_obj
+                                            .get("places")
+                                            .and_then(|v| serde_json::from_value(v.clone()).ok())
+                                            .unwrap_or_default();
+
+                                        // Place id -> archetype, so events can happen somewhere that suits them
+                                        let place_archetypes: HashMap<String, Archetype> = gen_events_obj
+                                            .get("archetypes")
+                                            .and_then(|v| serde_json::from_value(v.clone()).ok())
+                                            .unwrap_or_default();
+
                                         println!("GEN_EVENTS requested for: {} events", n);
 
-                                            let (sat, new_events) = gen_event(events.clone(), characters.clone()).await;
+                                            let (sat, new_events) = gen_event(events.clone(), characters.clone(), &rules, &place_names, &place_archetypes).await;
                                             println!("Generated: {:?}", new_events);
 
+                                        // Anyone born in the new event joins the roster
+                                        let newborns: Vec<Character> = new_events
+                                            .iter()
+                                            .flat_map(|e| &e.characters)
+                                            .filter(|c| c.born.is_some() && !characters.iter().any(|k| k.id == c.id))
+                                            .cloned()
+                                            .collect();
+                                        characters.extend(newborns);
+
+                                        // Who holds each throne once this event's deaths are counted
+                                        let rulers = current_rulers(&characters, &rules.relationships, &new_events);
+
+                                        // What each new event changes, in words the UI can show
+                                        let naming = Naming { characters: &characters, places: &place_names };
+                                        let outcomes: HashMap<&str, Vec<String>> = new_events
+                                            .iter()
+                                            .map(|e| {
+                                                let outcomes = e.effects.iter().filter_map(|eff| semantics(eff.kind())?.describe(eff, &naming));
+                                                (e.id.as_str(), outcomes.map(|o| format!("The {} should {}.", e._type, o)).collect())
+                                            })
+                                            .collect();
+
                                         let response = json!({
-                                            "GEN_EVENTS": { "sat": sat, "_events": new_events }
+                                            "GEN_EVENTS": { "sat": sat, "_events": new_events, "characters": characters, "rulers": rulers, "outcomes": outcomes }
                                         }).to_string();
 
                                         if let Err(e) = stream.write_all(response.as_bytes()).await {
@@ -80,6 +176,89 @@ pub async fn handle_client(mut stream: TcpStream) {
                                             break;
                                         }
 
+                                        continue;
+                                    }
+                                    else if let Some(gen_name_obj) = parsed_json.get("GEN_NAME") {
+                                        // A fresh name for someone born into "faction" mid-game
+                                        let faction = gen_name_obj.get("faction").and_then(|v| v.as_str()).unwrap_or_default();
+                                        let characters: Vec<Character> = gen_name_obj
+                                            .get("characters")
+                                            .and_then(|v| serde_json::from_value(v.clone()).ok())
+                                            .unwrap_or_default();
+                                        let events: Vec<Event> = gen_name_obj
+                                            .get("events")
+                                            .and_then(|v| serde_json::from_value(v.clone()).ok())
+                                            .unwrap_or_default();
+                                        let seed = gen_name_obj
+                                            .get("seed")
+                                            .and_then(|v| v.as_u64())
+                                            .unwrap_or_else(rand::random);
+                                        let name_params: NameParams = match params(gen_name_obj, "name parameters") {
+                                            Ok(name_params) => name_params,
+                                            Err(e) => {
+                                                let response = json!({ "GEN_NAME": { "error": e } }).to_string();
+                                                if let Err(e) = stream.write_all(response.as_bytes()).await {
+                                                    eprintln!("Failed to send GEN_NAME response: {}", e);
+                                                    break;
+                                                }
+                                                continue;
+                                            }
+                                        };
+
+                                        let mut names = NameGenerator::new(names().characters(), &name_params, seed);
+                                        for character in &characters {
+                                            names.reserve(&character.name);
+                                        }
+                                        names.reserve_events(&events);
+
+                                        let name = names.generate(faction);
+                                        let id = name.as_ref().map(|_| fresh_id("ch", &mut rand::rng()));
+                                        let response = json!({
+                                            "GEN_NAME": { "faction": faction, "name": name, "id": id }
+                                        }).to_string();
+
+                                        if let Err(e) = stream.write_all(response.as_bytes()).await {
+                                            eprintln!("Failed to send GEN_NAME response: {}", e);
+                                            break;
+                                        }
+
+                                        continue;
+                                    }
+                                    else if let Some(owners_obj) = parsed_json.get("OWNERS") {
+                                        // Who could hold "place" as "event" begins
+                                        let events: Vec<Event> = owners_obj
+                                            .get("events")
+                                            .and_then(|v| serde_json::from_value(v.clone()).ok())
+                                            .unwrap_or_default();
+                                        let characters: Vec<Character> = owners_obj
+                                            .get("characters")
+                                            .and_then(|v| serde_json::from_value(v.clone()).ok())
+                                            .unwrap_or_default();
+                                        let rules: TimelineRules = match params(owners_obj, "timeline rules") {
+                                            Ok(rules) => rules,
+                                            Err(e) => {
+                                                let response = json!({ "OWNERS": { "error": e } }).to_string();
+                                                if let Err(e) = stream.write_all(response.as_bytes()).await {
+                                                    eprintln!("Failed to send OWNERS response: {}", e);
+                                                    break;
+                                                }
+                                                continue;
+                                            }
+                                        };
+                                        let place = owners_obj.get("place").and_then(|v| v.as_str()).unwrap_or_default();
+                                        let event = owners_obj.get("event").and_then(|v| v.as_str()).unwrap_or_default();
+
+                                        let owners = possible_owners(&events, &characters, &rules, place, event);
+
+                                        let response = json!({
+                                            "OWNERS": { "place": place, "event": event, "owners": owners }
+                                        }).to_string();
+
+                                        if let Err(e) = stream.write_all(response.as_bytes()).await {
+                                            eprintln!("Failed to send OWNERS response: {}", e);
+                                            break;
+                                        }
+
                                         continue;
                                     }
                                 }
@@ -0,0 +1,329 @@
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter};
+use serde_json::{json, Value};
+
+use crate::types::{Archetype, Feature, FeatureKind, Map, Ownership, Place, PlaceInfo, Region, RoadEdge, RoadNetwork};
+use crate::utils::ids::place_id;
+use crate::utils::projection::Projection;
+use crate::utils::roads::straight_road;
+
+/// Turn a game-space point into a GeoJSON position: `[lng, lat]` when the map
+/// has a projection, otherwise the game coordinates themselves
+fn position(map: &Map, p: (f64, f64)) -> Value {
+    match map.projection {
+        Some(projection) => {
+            let (lat, lng) = projection.inverse(p);
+            json!([lng, lat])
+        }
+        None => json!([p.0, p.1]),
+    }
+}
+
+fn line(map: &Map, points: &[(f64, f64)]) -> Value {
+    Value::Array(points.iter().map(|&p| position(map, p)).collect())
+}
+
+/// Export a map as a GeoJSON FeatureCollection.
+///
+/// Places are Points and roads are LineStrings, in real-world WGS84
+/// coordinates with the game-space coordinates kept as a property. Maps
+/// without a projection are exported in game space and marked as such.
+pub fn map_to_geojson(map: &Map, ownership: &Ownership) -> Value {
+    let faction = |i: usize| map.locations.get(i).and_then(|p| ownership.get(p)).cloned();
+    let region = |i: usize| map.regions.iter().find(|r| r.places.contains(&i)).map(|r| r.name.clone());
+    let mut features = Vec::new();
+
+    for (i, place) in map.locations.iter().enumerate() {
+        features.push(json!({
+            "type": "Feature",
+            "geometry": { "type": "Point", "coordinates": position(map, place.location) },
+            "properties": {
+                "kind": "place",
+                "id": place.id,
+                "name": place.name,
+                "original_name": place.info.original_name,
+                "archetype": place.archetype,
+                "faction": ownership.get(place),
+                "types": place.info.types,
+                "rating": place.info.rating,
+                "vicinity": place.info.vicinity,
+                "region": region(i),
+                "game_coordinates": [place.location.0, place.location.1],
+            }
+        }));
+    }
+
+    for road in &map.roads.edges {
+        let (from, to) = (faction(road.from), faction(road.to));
+        features.push(json!({
+            "type": "Feature",
+            "geometry": { "type": "LineString", "coordinates": line(map, &road.path) },
+            "properties": {
+                "kind": "road",
+                "from": map.locations.get(road.from).map(|p| &p.name),
+                "to": map.locations.get(road.to).map(|p| &p.name),
+                "from_id": map.locations.get(road.from).map(|p| &p.id),
+                "to_id": map.locations.get(road.to).map(|p| &p.id),
+                "length_m": road.length_m,
+                "travel_time_s": road.travel_time_s,
+                "highway": road.highway,
+                // A road belongs to a faction only when it holds both ends
+                "faction": if from == to { from.clone() } else { None },
+                "factions": [from, to],
+                "game_coordinates": road.path,
+            }
+        }));
+    }
+
+    for feature in &map.features {
+        let geometry = if feature.kind.is_area() {
+            let mut ring: Vec<(f64, f64)> = feature.geometry.clone();
+            if let Some(&first) = ring.first() {
+                ring.push(first);
+            }
+            json!({ "type": "Polygon", "coordinates": [line(map, &ring)] })
+        } else {
+            json!({ "type": "LineString", "coordinates": line(map, &feature.geometry) })
+        };
+        features.push(json!({
+            "type": "Feature",
+            "geometry": geometry,
+            "properties": { "kind": feature.kind, "name": feature.name }
+        }));
+    }
+
+    json!({
+        "type": "FeatureCollection",
+        // Foreign member: RFC 7946 keeps "coordinates" for geometry objects
+        "chrono:crs": if map.projection.is_some() { "wgs84" } else { "game" },
+        "projection": map.projection,
+        "features": features,
+    })
+}
+
+fn parse_position(v: &Value) -> Result<(f64, f64), String> {
+    match v.as_array().map(|a| a.as_slice()) {
+        Some([x, y, ..]) => Ok((
+            x.as_f64().ok_or("Non-numeric coordinate")?,
+            y.as_f64().ok_or("Non-numeric coordinate")?,
+        )),
+        _ => Err(format!("Invalid position: {}", v)),
+    }
+}
+
+fn parse_line(v: &Value) -> Result<Vec<(f64, f64)>, String> {
+    v.as_array()
+        .ok_or_else(|| format!("Invalid line: {}", v))?
+        .iter()
+        .map(parse_position)
+        .collect()
+}
+
+/// Load a map from a GeoJSON FeatureCollection, e.g. one exported by
+/// `map_to_geojson` and hand-edited in QGIS.
+///
+/// Geometry is authoritative: real-world coordinates are re-projected with the
+/// stored projection, or a fresh one fitted to the places if there is none.
+/// Roads are matched to places by the `from_id`/`to_id` ids, then the
+/// `from`/`to` names, falling back to the nearest place to each end. Returns the ownership read from `faction`.
+pub fn map_from_geojson(value: &Value) -> Result<(Map, Ownership), String> {
+    let features = value["features"].as_array().ok_or("Not a FeatureCollection")?;
+    // Files written before "chrono:crs" kept it under "coordinates"
+    let crs = value["chrono:crs"].as_str().or(value["coordinates"].as_str());
+    let in_game_space = crs == Some("game");
+
+    // Step 1: Collect raw geometry
+    let mut places: Vec<(Place, Option<String>)> = Vec::new();
+    let mut regions: Vec<Region> = Vec::new();
+    let mut roads: Vec<(Value, Vec<(f64, f64)>)> = Vec::new();
+    let mut terrain: Vec<Feature> = Vec::new();
+
+    for feature in features {
+        let props = &feature["properties"];
+        let geometry = &feature["geometry"];
+        let coords = &geometry["coordinates"];
+
+        match (geometry["type"].as_str(), props["kind"].as_str()) {
+            (Some("Point"), _) => {
+                let name = props["name"].as_str().ok_or("Place without a name")?.to_string();
+                let info = PlaceInfo {
+                    types: serde_json::from_value(props["types"].clone()).unwrap_or_default(),
+                    rating: props["rating"].as_f64(),
+                    vicinity: props["vicinity"].as_str().map(str::to_string),
+                    original_name: props["original_name"].as_str().map(str::to_string),
+                };
+                let archetype: Archetype = serde_json::from_value(props["archetype"].clone()).unwrap_or_default();
+                let location = parse_position(coords)?;
+                // Places drawn by hand in QGIS have no id yet
+                let id = props["id"].as_str().map(str::to_string).unwrap_or_else(|| place_id(&name, location));
+                let place = Place { id, name, location, archetype, info };
+
+                if let Some(region_name) = props["region"].as_str() {
+                    match regions.iter_mut().find(|r| r.name == region_name) {
+                        Some(region) => region.places.push(places.len()),
+                        None => regions.push(Region { name: region_name.to_string(), places: vec![places.len()] }),
+                    }
+                }
+                places.push((place, props["faction"].as_str().map(str::to_string)));
+            }
+            (Some("LineString"), Some("road") | None) => {
+                roads.push((props.clone(), parse_line(coords)?));
+            }
+            (Some("LineString"), Some(kind)) | (Some("Polygon"), Some(kind)) => {
+                // Layers this game doesn't know about are skipped
+                let Ok(kind) = serde_json::from_value::<FeatureKind>(json!(kind)) else {
+                    continue;
+                };
+                let mut points = if geometry["type"] == "Polygon" {
+                    parse_line(&coords[0])?
+                } else {
+                    parse_line(coords)?
+                };
+                // Rings are closed in GeoJSON but not in Map
+                if kind.is_area() && points.len() > 1 && points.first() == points.last() {
+                    points.pop();
+                }
+                terrain.push(Feature { kind, name: props["name"].as_str().map(str::to_string), geometry: points });
+            }
+            _ => {}
+        }
+    }
+
+    if places.is_empty() {
+        return Err("No places found".into());
+    }
+
+    // Step 2: Work out how to get into game space. GeoJSON positions are [lng, lat].
+    let projection = if in_game_space {
+        None
+    } else {
+        let stored: Option<Projection> = serde_json::from_value(value["projection"].clone()).ok();
+        Some(stored.unwrap_or_else(|| {
+            let real: Vec<(f64, f64)> = places.iter().map(|(p, _)| (p.location.1, p.location.0)).collect();
+            Projection::fit(&real, 0.9)
+        }))
+    };
+    let to_game = |(x, y): (f64, f64)| match projection {
+        Some(projection) => projection.forward((y, x)),
+        None => (x, y),
+    };
+
+    let mut ownership = Ownership::new();
+    let locations: Vec<Place> = places
+        .into_iter()
+        .map(|(place, faction)| {
+            let place = Place { location: to_game(place.location), ..place };
+            if let Some(faction) = faction {
+                ownership.insert(place.clone(), faction);
+            }
+            place
+        })
+        .collect();
+
+    // Step 3: Link roads back to places
+    let nearest = |p: (f64, f64)| {
+        (0..locations.len())
+            .min_by(|&a, &b| {
+                let d = |i: usize| (locations[i].location.0 - p.0).powi(2) + (locations[i].location.1 - p.1).powi(2);
+                d(a).total_cmp(&d(b))
+            })
+            .unwrap()
+    };
+    let by_name = |v: &Value| v.as_str().and_then(|n| locations.iter().position(|p| p.name == n));
+    let by_id = |v: &Value| v.as_str().and_then(|id| locations.iter().position(|p| p.id == id));
+
+    let mut edges = Vec::new();
+    for (props, line) in roads {
+        let path: Vec<(f64, f64)> = line.into_iter().map(to_game).collect();
+        let (Some(&first), Some(&last)) = (path.first(), path.last()) else {
+            continue;
+        };
+        let from = by_id(&props["from_id"]).or_else(|| by_name(&props["from"])).unwrap_or_else(|| nearest(first));
+        let to = by_id(&props["to_id"]).or_else(|| by_name(&props["to"])).unwrap_or_else(|| nearest(last));
+
+        // Hand-drawn roads have no provider lengths; estimate them
+        let estimate = straight_road(&locations, from, to, projection.as_ref());
+        edges.push(RoadEdge {
+            from,
+            to,
+            length_m: props["length_m"].as_f64().unwrap_or(estimate.length_m),
+            travel_time_s: props["travel_time_s"].as_f64().unwrap_or(estimate.travel_time_s),
+            highway: props["highway"].as_bool().unwrap_or(false),
+            path,
+        });
+    }
+
+    let features = terrain
+        .into_iter()
+        .map(|feature| Feature {
+            geometry: feature.geometry.into_iter().map(to_game).collect(),
+            ..feature
+        })
+        .collect();
+
+    let map = Map { locations, roads: RoadNetwork { edges }, projection, features, regions };
+    Ok((map, ownership))
+}
+
+/// Write a Map and its ownership to a GeoJSON file
+pub fn write_geojson(map: &Map, ownership: &Ownership, path: &str) -> io::Result<()> {
+    let file = File::create(path)?;
+    let writer = BufWriter::new(file);
+    serde_json::to_writer_pretty(writer, &map_to_geojson(map, ownership))?;
+    Ok(())
+}
+
+/// Read a Map and its ownership from a GeoJSON file
+pub fn read_geojson(path: &str) -> io::Result<(Map, Ownership)> {
+    let file = File::open(path)?;
+    let reader = BufReader::new(file);
+    let value: Value = serde_json::from_reader(reader)?;
+    map_from_geojson(&value).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let projection = Projection { origin: (51.45, -2.59), scale: 1.0 / 1500.0 };
+        let map = Map {
+            locations: vec![
+                Place { id: "a".to_string(), name: "A".to_string(), location: (0.1, 0.2), archetype: Archetype::Castle, info: PlaceInfo::default() },
+                Place { id: "b".to_string(), name: "B".to_string(), location: (-0.4, 0.3), archetype: Archetype::Castle, info: PlaceInfo::default() },
+            ],
+            roads: RoadNetwork {
+                edges: vec![RoadEdge {
+                    from: 0,
+                    to: 1,
+                    length_m: 900.0,
+                    travel_time_s: 120.0,
+                    highway: false,
+                    path: vec![(0.1, 0.2), (-0.1, 0.3), (-0.4, 0.3)],
+                }],
+            },
+            projection: Some(projection),
+            features: vec![],
+            regions: vec![],
+        };
+        let mut ownership = Ownership::new();
+        ownership.insert(map.locations[0].clone(), "g".to_string());
+
+        let value = map_to_geojson(&map, &ownership);
+        // Only geometry objects may carry "coordinates"
+        assert_eq!(value["chrono:crs"], "wgs84");
+        assert!(value.get("coordinates").is_none());
+        let (loaded, loaded_ownership) = map_from_geojson(&value).unwrap();
+
+        assert_eq!(loaded.locations.len(), 2);
+        for (a, b) in map.locations.iter().zip(&loaded.locations) {
+            assert!((a.location.0 - b.location.0).abs() < 1e-9 && (a.location.1 - b.location.1).abs() < 1e-9);
+            assert_eq!(b.archetype, Archetype::Castle);
+            assert_eq!(a.id, b.id);
+        }
+        assert_eq!((loaded.roads.edges[0].from, loaded.roads.edges[0].to), (0, 1));
+        assert_eq!(loaded.roads.edges[0].length_m, 900.0);
+        assert_eq!(loaded_ownership.get(&loaded.locations[0]), Some(&"g".to_string()));
+    }
+}
@@ -1,14 +1,62 @@
-use crate::types::{Map, Place};
+use crate::types::{Archetype, Feature, Map, Place, PlaceInfo, Region, RoadEdge, RoadNetwork};
+use crate::utils::archetypes::classify;
+use crate::utils::ids::place_id;
+use crate::utils::projection::Projection;
+use crate::utils::roads::link_routes;
+use std::fmt;
 use std::fs::File;
 use std::io::{self, BufReader, BufWriter};
 use serde::{Serialize, Deserialize};
+use serde::de::{self, SeqAccess, Visitor};
+
+/// Extra place data, sent after the positional `(name, location)` pair
+#[derive(Serialize, Deserialize)]
+struct PlaceMeta {
+    #[serde(default)]
+    id: String,
+    #[serde(default)]
+    archetype: Archetype,
+    #[serde(flatten)]
+    info: PlaceInfo,
+}
 
 impl Serialize for Place {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
     {
-        (&self.name, &self.location).serialize(serializer)
+        let meta = PlaceMeta { id: self.id.clone(), archetype: self.archetype, info: self.info.clone() };
+        (&self.name, &self.location, meta).serialize(serializer)
+    }
+}
+
+struct PlaceVisitor;
+
+impl<'de> Visitor<'de> for PlaceVisitor {
+    type Value = Place;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a [name, location, meta?] sequence")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Place, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let name: String = seq.next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        let location: (f64, f64) = seq.next_element()?
+            .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+        // Places saved before metadata was kept are classified from their name alone
+        let meta: PlaceMeta = seq.next_element()?.unwrap_or_else(|| PlaceMeta {
+            id: String::new(),
+            archetype: classify(&name, &PlaceInfo::default()),
+            info: PlaceInfo::default(),
+        });
+        // Places saved before ids get one from what they were saved as
+        let id = if meta.id.is_empty() { place_id(&name, location) } else { meta.id };
+
+        Ok(Place { id, name, location, archetype: meta.archetype, info: meta.info })
     }
 }
 
@@ -17,17 +65,76 @@ impl<'de> Deserialize<'de> for Place {
     where
         D: serde::Deserializer<'de>,
     {
-        let (name, location) = <(String, (f64, f64))>::deserialize(deserializer)?;
-        Ok(Place { name, location })
+        deserializer.deserialize_seq(PlaceVisitor)
     }
 }
 
+/// Extra map data, sent after the positional `(locations, routes)` pair so
+/// older readers that only look at the first two elements keep working.
+#[derive(Serialize, Deserialize, Default)]
+struct MapMeta {
+    #[serde(default)]
+    roads: Vec<RoadEdge>,
+    #[serde(default)]
+    projection: Option<Projection>,
+    #[serde(default)]
+    features: Vec<Feature>,
+    #[serde(default)]
+    regions: Vec<Region>,
+}
+
 impl Serialize for Map {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
     {
-        (&self.locations, &self.routes).serialize(serializer)
+        let meta = MapMeta {
+            roads: self.roads.edges.clone(),
+            projection: self.projection,
+            features: self.features.clone(),
+            regions: self.regions.clone(),
+        };
+        (&self.locations, self.roads.routes(), meta).serialize(serializer)
+    }
+}
+
+struct MapVisitor;
+
+impl<'de> Visitor<'de> for MapVisitor {
+    type Value = Map;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a [locations, routes, meta?] sequence")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Map, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let locations: Vec<Place> = seq.next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        let routes: Vec<Vec<(f64, f64)>> = seq.next_element()?
+            .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+        let meta: MapMeta = seq.next_element()?.unwrap_or_default();
+        let projection = meta.projection;
+        let features = meta.features;
+        let regions = meta.regions;
+
+        let roads = if meta.roads.len() == routes.len() {
+            // Re-attach each polyline to the edge it was serialised with
+            RoadNetwork {
+                edges: meta.roads
+                    .into_iter()
+                    .zip(routes)
+                    .map(|(edge, path)| RoadEdge { path, ..edge })
+                    .collect(),
+            }
+        } else {
+            // Legacy maps: routes with no link back to places
+            link_routes(&locations, routes)
+        };
+
+        Ok(Map { locations, roads, projection, features, regions })
     }
 }
 
@@ -36,8 +143,7 @@ impl<'de> Deserialize<'de> for Map {
     where
         D: serde::Deserializer<'de>,
     {
-        let (locations, routes) = <(Vec<Place>, Vec<Vec<(f64, f64)>>)>::deserialize(deserializer)?;
-        Ok(Map { locations, routes })
+        deserializer.deserialize_seq(MapVisitor)
     }
 }
 
@@ -1,2 +1,3 @@
 pub mod client;
+pub mod geojson;
 pub mod io;
\ No newline at end of file
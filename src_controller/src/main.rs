@@ -12,9 +12,19 @@ use std::error::Error;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use crate::interval::plot::{add_constraint_and_get_interval};
 use crate::io::client::handle_client;
+use crate::utils::event_types::event_types;
+use crate::utils::factions::factions;
+use crate::utils::names::names;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
+    // Check the data files now rather than on the first request
+    println!(
+        "Loaded {} factions, {} names and {} event types",
+        factions().iter().count(),
+        names().characters().len(),
+        event_types().iter().count()
+    );
     init_connection().await;
     Ok(())
 }
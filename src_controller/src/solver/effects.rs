@@ -0,0 +1,591 @@
+use std::collections::HashMap;
+use z3::ast::{Bool, Int};
+
+use crate::solver::encoding::Encoding;
+use crate::types::{Character, Effect, EffectKind, Event, RelationKind, Role};
+use crate::utils::factions::factions;
+
+/// A fact that effects change over the timeline. Each is tracked per subject:
+/// a character, a faction or a place.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Fluent {
+    /// Character -> `Alive` or `Dead`
+    Alive,
+    /// Character -> `Unborn` or `Born`
+    Born,
+    /// Character -> their spouse
+    Married,
+    /// Faction -> its ruler
+    RulerOf,
+    /// Place -> the faction holding it
+    OwnsPlace,
+    /// Character -> `Home` or `Exiled`
+    Exiled,
+}
+
+/// What a fluent can be
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Value {
+    Alive,
+    Dead,
+    Unborn,
+    Born,
+    Home,
+    Exiled,
+    /// A character or faction, by id
+    Id(String),
+}
+
+impl Fluent {
+    /// What the fluent of `subject` is before any event, if anything
+    pub fn initial(self, ctx: &Encoding, subject: &str) -> Option<Value> {
+        match self {
+            Fluent::Alive => Some(Value::Alive),
+            Fluent::Exiled => Some(Value::Home),
+            // Anyone with a birth in the timeline starts unborn
+            Fluent::Born if ctx.changes(self, subject).is_empty() => Some(Value::Born),
+            Fluent::Born => Some(Value::Unborn),
+            // A spouse relationship binds both partners, whichever way it was given
+            Fluent::Married => ctx.rules.relationships.iter().find_map(|r| match r.kind {
+                RelationKind::Spouse if r.from == subject => Some(Value::Id(r.to.clone())),
+                RelationKind::Spouse if r.to == subject => Some(Value::Id(r.from.clone())),
+                _ => None,
+            }),
+            Fluent::RulerOf => ctx
+                .chars
+                .iter()
+                .find(|c| c.faction == subject && c.role == Some(Role::Ruler))
+                .map(|c| Value::Id(c.id.clone())),
+            Fluent::OwnsPlace => ctx.rules.ownership.get(subject).map(|f| Value::Id(f.clone())),
+        }
+    }
+}
+
+/// True when `character` is born and alive at `at`, counting anything that
+/// happens to them then
+pub fn alive_at(ctx: &Encoding, character: &str, at: &Int) -> Bool {
+    Bool::and(&[
+        ctx.is_at(Fluent::Alive, character, &Value::Alive, at),
+        ctx.is_at(Fluent::Born, character, &Value::Born, at),
+    ])
+}
+
+/// Display names for what effects refer to by id
+pub struct Naming<'a> {
+    pub characters: &'a [Character],
+    /// Place id -> display name
+    pub places: &'a HashMap<String, String>,
+}
+
+impl Naming<'_> {
+    pub fn character(&self, id: &str) -> String {
+        self.characters.iter().find(|c| c.id == id).map_or(id.to_string(), |c| c.name.clone())
+    }
+
+    pub fn place(&self, id: &str) -> String {
+        self.places.get(id).cloned().unwrap_or(id.to_string())
+    }
+
+    pub fn faction(&self, id: &str) -> String {
+        factions().get(id).map_or(id.to_string(), |f| f.name.clone())
+    }
+}
+
+/// What an effect means for the timeline. Each kind of effect has one,
+/// listed in `REGISTRY`; the solver knows nothing about effects beyond it.
+pub trait EffectSemantics: Sync {
+    fn kind(&self) -> EffectKind;
+
+    /// Fluents the effect changes
+    fn fluents(&self) -> &'static [Fluent];
+
+    /// Record the changes `effect` makes to its fluents at `event`
+    fn declare(&self, ctx: &mut Encoding, event: &Event, effect: &Effect);
+
+    /// Assert what has to be true for `effect` to happen at `event`. Runs once
+    /// every effect has declared its changes.
+    fn constrain(&self, ctx: &Encoding, event: &Event, effect: &Effect);
+
+    /// Whether `character` may take part in the event without being alive at
+    /// the end of it. Everyone else taking part must be.
+    fn excuses(&self, _effect: &Effect, _character: &str) -> bool {
+        false
+    }
+
+    /// What the event should bring about, completing "The battle should …",
+    /// e.g. "cause Bob to die". `None` if `effect` is not of this kind.
+    fn describe(&self, effect: &Effect, naming: &Naming) -> Option<String>;
+}
+
+/// Every effect the solver understands
+pub static REGISTRY: &[&dyn EffectSemantics] =
+    &[&Death, &Transfer, &Birth, &Marriage, &Coronation, &Exile, &Return, &Injury, &Resurrection];
+
+/// The semantics registered for `kind`
+pub fn semantics(kind: EffectKind) -> Option<&'static dyn EffectSemantics> {
+    REGISTRY.iter().find(|s| s.kind() == kind).copied()
+}
+
+/// The one character an effect acts on, for effects that take one
+fn subject(effect: &Effect) -> Option<&str> {
+    match effect.characters()[..] {
+        [c] => Some(c),
+        _ => None,
+    }
+}
+
+pub struct Death;
+
+impl EffectSemantics for Death {
+    fn kind(&self) -> EffectKind {
+        EffectKind::Death
+    }
+
+    fn fluents(&self) -> &'static [Fluent] {
+        &[Fluent::Alive]
+    }
+
+    fn declare(&self, ctx: &mut Encoding, event: &Event, effect: &Effect) {
+        if let Some(c) = subject(effect) {
+            ctx.change(Fluent::Alive, c, event, Value::Dead);
+        }
+    }
+
+    /// Only the living die
+    fn constrain(&self, ctx: &Encoding, event: &Event, effect: &Effect) {
+        let Some(c) = subject(effect) else {
+            return;
+        };
+        let t = ctx.time(&event.id);
+        ctx.solver.assert(ctx.is_before(Fluent::Alive, c, &Value::Alive, t));
+    }
+
+    /// The victim is only alive up to their death
+    fn excuses(&self, effect: &Effect, character: &str) -> bool {
+        subject(effect) == Some(character)
+    }
+
+    fn describe(&self, effect: &Effect, naming: &Naming) -> Option<String> {
+        Some(format!("cause {} to die", naming.character(subject(effect)?)))
+    }
+}
+
+pub struct Resurrection;
+
+impl EffectSemantics for Resurrection {
+    fn kind(&self) -> EffectKind {
+        EffectKind::Resurrection
+    }
+
+    fn fluents(&self) -> &'static [Fluent] {
+        &[Fluent::Alive]
+    }
+
+    fn declare(&self, ctx: &mut Encoding, event: &Event, effect: &Effect) {
+        if let Some(c) = subject(effect) {
+            ctx.change(Fluent::Alive, c, event, Value::Alive);
+        }
+    }
+
+    /// Only the dead rise
+    fn constrain(&self, ctx: &Encoding, event: &Event, effect: &Effect) {
+        let Some(c) = subject(effect) else {
+            return;
+        };
+        let t = ctx.time(&event.id);
+        ctx.solver.assert(ctx.is_before(Fluent::Alive, c, &Value::Dead, t));
+    }
+
+    fn describe(&self, effect: &Effect, naming: &Naming) -> Option<String> {
+        Some(format!("raise {} from the dead", naming.character(subject(effect)?)))
+    }
+}
+
+pub struct Birth;
+
+impl EffectSemantics for Birth {
+    fn kind(&self) -> EffectKind {
+        EffectKind::Birth
+    }
+
+    fn fluents(&self) -> &'static [Fluent] {
+        &[Fluent::Born]
+    }
+
+    fn declare(&self, ctx: &mut Encoding, event: &Event, effect: &Effect) {
+        if let Some(c) = subject(effect) {
+            ctx.change(Fluent::Born, c, event, Value::Born);
+        }
+    }
+
+    /// Born once, and nothing involves them before it
+    fn constrain(&self, ctx: &Encoding, event: &Event, effect: &Effect) {
+        let Some(c) = subject(effect) else {
+            return;
+        };
+        if ctx.changes(Fluent::Born, c).iter().any(|b| b.event != event.id) {
+            ctx.solver.assert(Bool::from_bool(false));
+            return;
+        }
+        let t_born = ctx.time(&event.id);
+        for e in ctx.events.iter().filter(|e| e.id != event.id && ctx.involves(e, c)) {
+            ctx.solver.assert(ctx.time(&e.id).gt(t_born));
+        }
+    }
+
+    fn describe(&self, effect: &Effect, naming: &Naming) -> Option<String> {
+        Some(format!("see {} born", naming.character(subject(effect)?)))
+    }
+}
+
+pub struct Marriage;
+
+impl EffectSemantics for Marriage {
+    fn kind(&self) -> EffectKind {
+        EffectKind::Marriage
+    }
+
+    fn fluents(&self) -> &'static [Fluent] {
+        &[Fluent::Married]
+    }
+
+    fn declare(&self, ctx: &mut Encoding, event: &Event, effect: &Effect) {
+        if let Effect::Marriage(a, b) = effect {
+            ctx.change(Fluent::Married, a, event, Value::Id(b.clone()));
+            ctx.change(Fluent::Married, b, event, Value::Id(a.clone()));
+        }
+    }
+
+    /// Both partners are alive, and neither has a living spouse already
+    fn constrain(&self, ctx: &Encoding, event: &Event, effect: &Effect) {
+        let Effect::Marriage(a, b) = effect else {
+            return;
+        };
+        if a == b {
+            ctx.solver.assert(Bool::from_bool(false));
+            return;
+        }
+        let t = ctx.time(&event.id);
+        for (partner, other) in [(a, b), (b, a)] {
+            ctx.solver.assert(alive_at(ctx, partner, t));
+            for spouse in ctx.values(Fluent::Married, partner) {
+                let Value::Id(id) = &spouse else {
+                    continue;
+                };
+                if id == other {
+                    continue;
+                }
+                let still_wed = ctx.is_before(Fluent::Married, partner, &spouse, t);
+                ctx.solver.assert(still_wed.implies(alive_at(ctx, id, t).not()));
+            }
+        }
+    }
+
+    fn describe(&self, effect: &Effect, naming: &Naming) -> Option<String> {
+        let Effect::Marriage(a, b) = effect else {
+            return None;
+        };
+        Some(format!("see {} and {} married", naming.character(a), naming.character(b)))
+    }
+}
+
+pub struct Coronation;
+
+impl EffectSemantics for Coronation {
+    fn kind(&self) -> EffectKind {
+        EffectKind::Coronation
+    }
+
+    fn fluents(&self) -> &'static [Fluent] {
+        &[Fluent::RulerOf]
+    }
+
+    fn declare(&self, ctx: &mut Encoding, event: &Event, effect: &Effect) {
+        if let Some(c) = subject(effect)
+            && let Some(faction) = ctx.faction_of(c)
+        {
+            ctx.change(Fluent::RulerOf, faction, event, Value::Id(c.to_string()));
+        }
+    }
+
+    /// The crowned are alive for it, and whoever ruled before them is dead
+    fn constrain(&self, ctx: &Encoding, event: &Event, effect: &Effect) {
+        let Some(c) = subject(effect) else {
+            return;
+        };
+        let t = ctx.time(&event.id);
+        ctx.solver.assert(alive_at(ctx, c, t));
+        let Some(faction) = ctx.faction_of(c) else {
+            return;
+        };
+        for ruler in ctx.values(Fluent::RulerOf, faction) {
+            let Value::Id(id) = &ruler else {
+                continue;
+            };
+            if id == c {
+                continue;
+            }
+            let reigning = ctx.is_before(Fluent::RulerOf, faction, &ruler, t);
+            ctx.solver.assert(reigning.implies(alive_at(ctx, id, t).not()));
+        }
+    }
+
+    fn describe(&self, effect: &Effect, naming: &Naming) -> Option<String> {
+        Some(format!("see {} crowned", naming.character(subject(effect)?)))
+    }
+}
+
+pub struct Exile;
+
+impl EffectSemantics for Exile {
+    fn kind(&self) -> EffectKind {
+        EffectKind::Exile
+    }
+
+    fn fluents(&self) -> &'static [Fluent] {
+        &[Fluent::Exiled]
+    }
+
+    fn declare(&self, ctx: &mut Encoding, event: &Event, effect: &Effect) {
+        if let Some(c) = subject(effect) {
+            ctx.change(Fluent::Exiled, c, event, Value::Exiled);
+        }
+    }
+
+    /// Only someone alive and at home can be exiled, and until they return
+    /// they take part in nothing held at a place their faction holds. Events
+    /// without a place count as at home.
+    fn constrain(&self, ctx: &Encoding, event: &Event, effect: &Effect) {
+        let Some(c) = subject(effect) else {
+            return;
+        };
+        let t = ctx.time(&event.id);
+        ctx.solver.assert(alive_at(ctx, c, t));
+        ctx.solver.assert(ctx.is_before(Fluent::Exiled, c, &Value::Exiled, t).not());
+
+        let Some(faction) = ctx.faction_of(c) else {
+            return;
+        };
+        let moves = ctx.changes(Fluent::Exiled, c);
+        let elsewhere = ctx
+            .events
+            .iter()
+            .filter(|e| e.characters.iter().any(|p| p.id == c) && !moves.iter().any(|m| m.event == e.id));
+        for e in elsewhere {
+            let at = ctx.time(&e.id);
+            let at_home = match &e.place {
+                Some(place) => ctx.is_before(Fluent::OwnsPlace, place, &Value::Id(faction.to_string()), at),
+                None => Bool::from_bool(true),
+            };
+            ctx.solver.assert(ctx.is_before(Fluent::Exiled, c, &Value::Exiled, at).implies(at_home.not()));
+        }
+    }
+
+    fn describe(&self, effect: &Effect, naming: &Naming) -> Option<String> {
+        Some(format!("drive {} into exile", naming.character(subject(effect)?)))
+    }
+}
+
+pub struct Return;
+
+impl EffectSemantics for Return {
+    fn kind(&self) -> EffectKind {
+        EffectKind::Return
+    }
+
+    fn fluents(&self) -> &'static [Fluent] {
+        &[Fluent::Exiled]
+    }
+
+    fn declare(&self, ctx: &mut Encoding, event: &Event, effect: &Effect) {
+        if let Some(c) = subject(effect) {
+            ctx.change(Fluent::Exiled, c, event, Value::Home);
+        }
+    }
+
+    /// Only a living exile can come home
+    fn constrain(&self, ctx: &Encoding, event: &Event, effect: &Effect) {
+        let Some(c) = subject(effect) else {
+            return;
+        };
+        let t = ctx.time(&event.id);
+        ctx.solver.assert(alive_at(ctx, c, t));
+        ctx.solver.assert(ctx.is_before(Fluent::Exiled, c, &Value::Exiled, t));
+    }
+
+    fn describe(&self, effect: &Effect, naming: &Naming) -> Option<String> {
+        Some(format!("bring {} home from exile", naming.character(subject(effect)?)))
+    }
+}
+
+pub struct Injury;
+
+impl EffectSemantics for Injury {
+    fn kind(&self) -> EffectKind {
+        EffectKind::Injury
+    }
+
+    fn fluents(&self) -> &'static [Fluent] {
+        &[]
+    }
+
+    fn declare(&self, _ctx: &mut Encoding, _event: &Event, _effect: &Effect) {}
+
+    /// The injured live through it
+    fn constrain(&self, ctx: &Encoding, event: &Event, effect: &Effect) {
+        if let Some(c) = subject(effect) {
+            ctx.solver.assert(alive_at(ctx, c, ctx.time(&event.id)));
+        }
+    }
+
+    fn describe(&self, effect: &Effect, naming: &Naming) -> Option<String> {
+        Some(format!("leave {} wounded but alive", naming.character(subject(effect)?)))
+    }
+}
+
+pub struct Transfer;
+
+impl EffectSemantics for Transfer {
+    fn kind(&self) -> EffectKind {
+        EffectKind::Transfer
+    }
+
+    fn fluents(&self) -> &'static [Fluent] {
+        &[Fluent::OwnsPlace]
+    }
+
+    fn declare(&self, ctx: &mut Encoding, event: &Event, effect: &Effect) {
+        if let Effect::Transfer { place, to } = effect {
+            ctx.change(Fluent::OwnsPlace, place, event, Value::Id(to.clone()));
+        }
+    }
+
+    /// Anyone can take a place; two transfers can't happen at once, which the
+    /// solver checks for every fluent
+    fn constrain(&self, _ctx: &Encoding, _event: &Event, _effect: &Effect) {}
+
+    fn describe(&self, effect: &Effect, naming: &Naming) -> Option<String> {
+        let Effect::Transfer { place, to } = effect else {
+            return None;
+        };
+        Some(format!("leave {} in the hands of the {}", naming.place(place), naming.faction(to)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use z3::Solver;
+    use crate::types::TimelineRules;
+
+    #[test]
+    fn test_every_kind_registered_once() {
+        let kinds = [
+            EffectKind::Death,
+            EffectKind::Transfer,
+            EffectKind::Birth,
+            EffectKind::Marriage,
+            EffectKind::Coronation,
+            EffectKind::Exile,
+            EffectKind::Return,
+            EffectKind::Injury,
+            EffectKind::Resurrection,
+        ];
+        for kind in kinds {
+            assert_eq!(REGISTRY.iter().filter(|s| s.kind() == kind).count(), 1, "{:?}", kind);
+            assert_eq!(semantics(kind).map(|s| s.kind()), Some(kind));
+        }
+        assert_eq!(REGISTRY.len(), kinds.len());
+    }
+
+    #[test]
+    fn test_effects_change_only_their_fluents() {
+        let effects = vec![
+            Effect::Death("a".into()),
+            Effect::Transfer { place: "keep".into(), to: "t".into() },
+            Effect::Birth("b".into()),
+            Effect::Marriage("a".into(), "b".into()),
+            Effect::Coronation("a".into()),
+            Effect::Exile("a".into()),
+            Effect::Return("a".into()),
+            Effect::Injury("a".into()),
+            Effect::Resurrection("a".into()),
+        ];
+        let characters = vec![Character { id: "a".into(), name: "Ann".into(), faction: "g".into(), ..Default::default() }];
+        let events = vec![Event {
+            id: "e1".into(),
+            name: "e1".into(),
+            description: String::new(),
+            before: vec![],
+            start: 0.0,
+            end: 0.0,
+            _type: "normal".into(),
+            characters: vec![],
+            effects: vec![],
+            requires: vec![],
+            place: None,
+            track: 0.0,
+        }];
+        let (solver, rules) = (Solver::new(), TimelineRules::default());
+
+        for effect in &effects {
+            let semantics = semantics(effect.kind()).unwrap();
+            let mut ctx = Encoding::new(&solver, &events, &characters, &rules);
+            ctx.declare(semantics, &events[0], effect);
+            for ((fluent, _), _) in ctx.changed() {
+                assert!(semantics.fluents().contains(fluent), "{:?} changed {:?}", effect, fluent);
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "does not declare it")]
+    fn test_undeclared_change_rejected() {
+        let events = vec![Event {
+            id: "e1".into(),
+            name: "e1".into(),
+            description: String::new(),
+            before: vec![],
+            start: 0.0,
+            end: 0.0,
+            _type: "normal".into(),
+            characters: vec![],
+            effects: vec![],
+            requires: vec![],
+            place: None,
+            track: 0.0,
+        }];
+        let (solver, rules) = (Solver::new(), TimelineRules::default());
+        let mut ctx = Encoding::new(&solver, &events, &[], &rules);
+        // Injury declares no fluents, so nothing may change outside `declare`
+        ctx.declare(&Injury, &events[0], &Effect::Injury("a".into()));
+        ctx.change(Fluent::Alive, "a", &events[0], Value::Dead);
+    }
+
+    #[test]
+    fn test_describe() {
+        let characters = vec![
+            Character { id: "ann".into(), name: "Ann".into(), faction: "g".into(), ..Default::default() },
+            Character { id: "bo".into(), name: "Bo".into(), faction: "g".into(), ..Default::default() },
+        ];
+        let places = HashMap::from([("keep".to_string(), "Old Keep".to_string())]);
+        let naming = Naming { characters: &characters, places: &places };
+        let describe = |effect: Effect| semantics(effect.kind()).unwrap().describe(&effect, &naming).unwrap();
+
+        assert_eq!(describe(Effect::Death("ann".into())), "cause Ann to die");
+        assert_eq!(describe(Effect::Transfer { place: "keep".into(), to: "t".into() }), "leave Old Keep in the hands of the Trolls");
+        assert_eq!(describe(Effect::Birth("bo".into())), "see Bo born");
+        assert_eq!(describe(Effect::Marriage("ann".into(), "bo".into())), "see Ann and Bo married");
+        assert_eq!(describe(Effect::Coronation("ann".into())), "see Ann crowned");
+        assert_eq!(describe(Effect::Exile("bo".into())), "drive Bo into exile");
+        assert_eq!(describe(Effect::Return("bo".into())), "bring Bo home from exile");
+        assert_eq!(describe(Effect::Injury("ann".into())), "leave Ann wounded but alive");
+        assert_eq!(describe(Effect::Resurrection("ann".into())), "raise Ann from the dead");
+        // Unknown ids are shown as they are
+        assert_eq!(describe(Effect::Death("cy".into())), "cause cy to die");
+
+        // An effect of another kind has nothing to say
+        assert_eq!(Marriage.describe(&Effect::Death("ann".into()), &naming), None);
+        assert_eq!(Transfer.describe(&Effect::Death("ann".into()), &naming), None);
+    }
+}
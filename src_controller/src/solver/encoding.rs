@@ -0,0 +1,237 @@
+use std::collections::HashMap;
+use z3::Solver;
+use z3::ast::{Bool, Int};
+
+use crate::solver::effects::{EffectSemantics, Fluent, Value};
+use crate::types::{Character, Effect, Event, TimelineRules};
+
+/// One effect setting a fluent to `to` at its event
+pub struct Change {
+    pub event: String,
+    pub at: Int,
+    pub to: Value,
+}
+
+/// Everything effects share while a timeline is encoded: the solver, each
+/// event's time, and how every fluent changes
+pub struct Encoding<'a> {
+    pub solver: &'a Solver,
+    pub events: &'a [Event],
+    pub chars: &'a [Character],
+    pub rules: &'a TimelineRules,
+    times: HashMap<String, Int>,
+    /// Every effect with the index of its event, counting deaths and births
+    /// recorded on the characters themselves
+    effects: Vec<(usize, Effect)>,
+    changes: HashMap<(Fluent, String), Vec<Change>>,
+    /// Fluents the effect being declared may change
+    declaring: &'static [Fluent],
+}
+
+impl<'a> Encoding<'a> {
+    /// Give each event a time in 0..=1000
+    pub fn new(solver: &'a Solver, events: &'a [Event], chars: &'a [Character], rules: &'a TimelineRules) -> Self {
+        let mut times = HashMap::new();
+        for e in events {
+            let t = Int::new_const(format!("t_{}", e.id));
+            solver.assert(t.ge(Int::from_i64(0)));
+            solver.assert(t.le(Int::from_i64(1000)));
+            times.insert(e.id.clone(), t);
+        }
+
+        let mut effects: Vec<(usize, Effect)> = Vec::new();
+        for (i, e) in events.iter().enumerate() {
+            effects.extend(e.effects.iter().map(|eff| (i, eff.clone())));
+        }
+        // A recorded birth or death counts too, unless its event already has the effect
+        for c in chars {
+            let recorded = [(&c.born, Effect::Birth(c.id.clone())), (&c.died, Effect::Death(c.id.clone()))];
+            for (event, effect) in recorded {
+                let Some(i) = event.as_ref().and_then(|id| events.iter().position(|e| &e.id == id)) else {
+                    continue;
+                };
+                let already = effects.iter().any(|(j, eff)| *j == i && eff.kind() == effect.kind() && eff.characters() == effect.characters());
+                if !already {
+                    effects.push((i, effect));
+                }
+            }
+        }
+
+        Encoding { solver, events, chars, rules, times, effects, changes: HashMap::new(), declaring: &[] }
+    }
+
+    pub fn times(&self) -> &HashMap<String, Int> {
+        &self.times
+    }
+
+    pub fn time(&self, event: &str) -> &Int {
+        &self.times[event]
+    }
+
+    /// Every effect in the timeline, with the event it happens in
+    pub fn effects(&self) -> impl Iterator<Item = (&'a Event, &Effect)> {
+        self.effects.iter().map(|(i, eff)| (&self.events[*i], eff))
+    }
+
+    /// Let `semantics` record the changes `effect` makes at `event`, holding
+    /// it to the fluents it declares
+    pub fn declare(&mut self, semantics: &dyn EffectSemantics, event: &Event, effect: &Effect) {
+        self.declaring = semantics.fluents();
+        semantics.declare(self, event, effect);
+        self.declaring = &[];
+    }
+
+    /// Record that `event` sets `fluent` of `subject` to `to`. Only the fluents
+    /// of the effect being declared may change.
+    pub fn change(&mut self, fluent: Fluent, subject: &str, event: &Event, to: Value) {
+        assert!(self.declaring.contains(&fluent), "{:?} changed by an effect that does not declare it", fluent);
+        let at = self.times[&event.id].clone();
+        let change = Change { event: event.id.clone(), at, to };
+        self.changes.entry((fluent, subject.to_string())).or_default().push(change);
+    }
+
+    pub fn changes(&self, fluent: Fluent, subject: &str) -> &[Change] {
+        self.changes.get(&(fluent, subject.to_string())).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Every fluent and subject some effect changes
+    pub fn changed(&self) -> impl Iterator<Item = (&(Fluent, String), &Vec<Change>)> {
+        self.changes.iter()
+    }
+
+    /// Every value `fluent` of `subject` ever takes, starting value first
+    pub fn values(&self, fluent: Fluent, subject: &str) -> Vec<Value> {
+        let mut values: Vec<Value> = fluent.initial(self, subject).into_iter().collect();
+        for change in self.changes(fluent, subject) {
+            if !values.contains(&change.to) {
+                values.push(change.to.clone());
+            }
+        }
+        values
+    }
+
+    /// True when `fluent` of `subject` is `value` just before time `at`: the
+    /// latest change before then set it, or there was none and it started so
+    pub fn is_before(&self, fluent: Fluent, subject: &str, value: &Value, at: &Int) -> Bool {
+        let changes = self.changes(fluent, subject);
+        let mut options: Vec<Bool> = Vec::new();
+        if fluent.initial(self, subject).as_ref() == Some(value) {
+            let none_before: Vec<Bool> = changes.iter().map(|c| c.at.ge(at)).collect();
+            options.push(Bool::and(&none_before));
+        }
+        for (i, change) in changes.iter().enumerate() {
+            if &change.to != value {
+                continue;
+            }
+            // Before `at`, with every other change either before it or not yet happened
+            let mut latest = vec![change.at.lt(at)];
+            for (j, other) in changes.iter().enumerate() {
+                if i != j {
+                    latest.push(Bool::or(&[other.at.lt(&change.at), other.at.ge(at)]));
+                }
+            }
+            options.push(Bool::and(&latest));
+        }
+        Bool::or(&options)
+    }
+
+    /// Like `is_before`, counting changes at `at` itself
+    pub fn is_at(&self, fluent: Fluent, subject: &str, value: &Value, at: &Int) -> Bool {
+        self.is_before(fluent, subject, value, &Int::add(&[at.clone(), Int::from_i64(1)]))
+    }
+
+    /// Faction of a character, from the roster or else the events they appear in
+    pub fn faction_of(&self, character: &str) -> Option<&'a str> {
+        self.chars
+            .iter()
+            .chain(self.events.iter().flat_map(|e| &e.characters))
+            .find(|c| c.id == character)
+            .map(|c| c.faction.as_str())
+    }
+
+    /// Whether `character` takes part in `event` or has something happen to them there
+    pub fn involves(&self, event: &Event, character: &str) -> bool {
+        event.characters.iter().any(|c| c.id == character)
+            || self
+                .effects()
+                .any(|(e, eff)| e.id == event.id && eff.characters().iter().any(|c| *c == character))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use z3::SatResult;
+    use crate::solver::effects::semantics;
+
+    /// Whether `claim` holds however the solver's free choices fall
+    fn proves(solver: &Solver, claim: Bool) -> bool {
+        solver.push();
+        solver.assert(claim.not());
+        let proven = solver.check() == SatResult::Unsat;
+        solver.pop(1);
+        proven
+    }
+
+    #[test]
+    fn test_is_before_and_is_at() {
+        // Ann dies at 10 and rises at 20
+        let events = vec![
+            Event {
+                id: "e1".to_string(),
+                name: "e1".to_string(),
+                description: String::new(),
+                before: vec![],
+                start: 0.0,
+                end: 0.0,
+                _type: "normal".to_string(),
+                characters: vec![],
+                effects: vec![Effect::Death("ann".into())],
+                requires: vec![],
+                place: None,
+                track: 0.0,
+            },
+            Event {
+                id: "e2".to_string(),
+                name: "e2".to_string(),
+                description: String::new(),
+                before: vec![],
+                start: 0.0,
+                end: 0.0,
+                _type: "normal".to_string(),
+                characters: vec![],
+                effects: vec![Effect::Resurrection("ann".into())],
+                requires: vec![],
+                place: None,
+                track: 0.0,
+            },
+        ];
+        let (solver, rules) = (Solver::new(), TimelineRules::default());
+        let mut ctx = Encoding::new(&solver, &events, &[], &rules);
+        for (e, eff) in events.iter().map(|e| (e, &e.effects[0])) {
+            ctx.declare(semantics(eff.kind()).unwrap(), e, eff);
+        }
+        solver.assert(ctx.time("e1")._eq(Int::from_i64(10)));
+        solver.assert(ctx.time("e2")._eq(Int::from_i64(20)));
+        let at = Int::from_i64;
+        let (alive, dead) = (Value::Alive, Value::Dead);
+
+        // No changes: Bo is as he started, whenever we look
+        assert!(proves(&solver, ctx.is_before(Fluent::Alive, "bo", &alive, &at(0))));
+        assert!(proves(&solver, ctx.is_at(Fluent::Alive, "bo", &alive, &at(1000))));
+        assert!(proves(&solver, ctx.is_before(Fluent::Alive, "bo", &dead, &at(500)).not()));
+
+        // A change exactly at `at` counts for `is_at` but not `is_before`
+        assert!(proves(&solver, ctx.is_before(Fluent::Alive, "ann", &alive, &at(10))));
+        assert!(proves(&solver, ctx.is_at(Fluent::Alive, "ann", &dead, &at(10))));
+        assert!(proves(&solver, ctx.is_at(Fluent::Alive, "ann", &alive, &at(10)).not()));
+
+        // Several changes: the latest before `at` wins
+        assert!(proves(&solver, ctx.is_before(Fluent::Alive, "ann", &dead, &at(15))));
+        assert!(proves(&solver, ctx.is_before(Fluent::Alive, "ann", &dead, &at(20))));
+        assert!(proves(&solver, ctx.is_at(Fluent::Alive, "ann", &alive, &at(20))));
+        assert!(proves(&solver, ctx.is_before(Fluent::Alive, "ann", &alive, &at(25))));
+        assert!(proves(&solver, ctx.is_before(Fluent::Alive, "ann", &dead, &at(25)).not()));
+        assert_eq!(ctx.values(Fluent::Alive, "ann"), vec![alive, dead]);
+    }
+}
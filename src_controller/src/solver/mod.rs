@@ -1 +1,3 @@
-pub mod solve;
\ No newline at end of file
+pub mod effects;
+pub mod encoding;
+pub mod solve;
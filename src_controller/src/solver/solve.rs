@@ -1,158 +1,187 @@
 use     z3::{Config, Context, Solver, ast::{Int, Bool}, SatResult};
-use crate::types::{Event, Character, Effect};
-use std::collections::HashMap;
+use crate::types::{Event, Character, Condition, Effect, Role, TimelineRules};
+use crate::solver::effects::{Fluent, Value, alive_at, semantics};
+use crate::solver::encoding::Encoding;
+use crate::utils::dynasty::line_of_succession;
+use crate::utils::ids::upgrade_legacy;
 
 pub fn isPossible(events: Vec<Event>, chars: Vec<Character>) -> bool {
+    is_possible_under(events, chars, &TimelineRules::default())
+}
+
+/// Like `isPossible`, also holding the timeline to `rules`: who owns each
+/// place before any event, so `Transfer` effects and `Holds` conditions can be
+/// checked, and whether every faction must keep a living ruler
+pub fn is_possible_under(mut events: Vec<Event>, mut chars: Vec<Character>, rules: &TimelineRules) -> bool {
+    upgrade_legacy(&mut events, &mut chars);
     let solver = Solver::new();
+    encode_timeline(&solver, &events, &chars, rules);
 
-    // Map event names to Z3 integer variables
-    let mut event_times: HashMap<String, Int> = HashMap::new();
-    for e in &events {
-        let t = Int::new_const(format!("t_{}", e.name));
-        // constrain times to 0..1000
-        solver.assert(&t.ge(&Int::from_i64(0)));
-        solver.assert(&t.le(&Int::from_i64(1000)));
-        event_times.insert(e.name.clone(), t);
+    // Check satisfiability
+    match solver.check() {
+        SatResult::Sat => true,
+        SatResult::Unsat => false,
+        SatResult::Unknown => false, // conservatively return false on unknown
     }
+}
 
-    // Collect all character names
-    let mut characters: Vec<String> = chars.iter().map(|c| c.name.clone()).collect();
+/// Every faction that could hold `place` as `event` begins, in some timeline
+/// consistent with the events. One owner means the timeline settles it; more
+/// than one means it depends on how unordered events play out. Empty if the
+/// timeline is impossible or there is no such event.
+pub fn possible_owners(
+    events: &[Event],
+    chars: &[Character],
+    rules: &TimelineRules,
+    place: &str,
+    event: &str,
+) -> Vec<String> {
+    let (mut events, mut chars) = (events.to_vec(), chars.to_vec());
+    upgrade_legacy(&mut events, &mut chars);
+    let events = events.as_slice();
 
-    // Map each character to an array of alive booleans for times 0..1000
-    let mut alive_vars: HashMap<String, Vec<Bool>> = HashMap::new();
-    for c in &characters {
-        let mut alive_vec = Vec::new();
-        for t in 0..=1000 {
-            let b = Bool::new_const(format!("alive_{}_{}", c, t));
-            alive_vec.push(b);
-        }
-        alive_vars.insert(c.clone(), alive_vec);
-    }
+    let solver = Solver::new();
+    let ctx = encode_timeline(&solver, events, &chars, rules);
+    // `event` may be an id or, from an older client, a name
+    let Some(at) = ctx
+        .times()
+        .get(event)
+        .or_else(|| events.iter().find(|e| e.name == event).and_then(|e| ctx.times().get(&e.id)))
+    else {
+        return vec![];
+    };
+
+    // Anyone who starts with the place or is handed it
+    ctx.values(Fluent::OwnsPlace, place)
+        .into_iter()
+        .filter(|faction| {
+            solver.push();
+            solver.assert(ctx.is_before(Fluent::OwnsPlace, place, faction, at));
+            let possible = solver.check() == SatResult::Sat;
+            solver.pop(1);
+            possible
+        })
+        .filter_map(|faction| match faction {
+            Value::Id(id) => Some(id),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Assert everything a timeline of `events` must satisfy, returning the
+/// encoding with each event's time. What each effect means is left to its
+/// `EffectSemantics`.
+fn encode_timeline<'a>(
+    solver: &'a Solver,
+    events: &'a [Event],
+    chars: &'a [Character],
+    rules: &'a TimelineRules,
+) -> Encoding<'a> {
+    let mut ctx = Encoding::new(solver, events, chars, rules);
 
     // Constraint 1: Event ordering
-    for e in &events {
-        let t1 = event_times.get(&e.name).unwrap();
+    for e in events {
+        let t1 = ctx.time(&e.id);
         for b in &e.before {
-            if let Some(t2) = event_times.get(b) {
+            if let Some(t2) = ctx.times().get(b) {
                 solver.assert(&t1.lt(t2));
             }
         }
     }
 
-
-
-    // Constraint 2: Death effects
-    for e in &events {
-        let t = event_times.get(&e.name).unwrap();
-        for eff in &e.effects {
-            if let Effect::Death(c_name) = eff {
-                let alive_vec = alive_vars.get(c_name).unwrap_or_else(|| {
-                    eprintln!("❌ No entry found for key: {c_name:?}");
-                    eprintln!("Current alive_vars keys: {:?}", alive_vars.keys());
-                    panic!("Missing key in alive_vars: {}", c_name);
-                });
-                for time in 0..=1000 {
-                    let time_int = Int::from_i64(time as i64);
-                    // If time >= event_time => character dead
-                    solver.assert(&time_int.ge(t).implies(&alive_vec[time].not()));
-                }
-            }
+    // Step 1: Every effect records how it changes the timeline's fluents,
+    // then asserts what it needs, now that all changes are known. An effect
+    // nothing is registered for can't be checked, so the timeline is refused.
+    let effects: Vec<(&Event, Effect)> = ctx.effects().map(|(e, eff)| (e, eff.clone())).collect();
+    for (e, eff) in &effects {
+        match semantics(eff.kind()) {
+            Some(effect) => ctx.declare(effect, e, eff),
+            None => solver.assert(Bool::from_bool(false)),
         }
     }
-
-    // Constraint 3 & 4: Life propagation
-    for c in &characters {
-        let alive_vec = alive_vars.get(c).unwrap();
-        // alive at t -> alive at t-1 (if t>0)
-        for t in 1..=1000 {
-            solver.assert(&alive_vec[t].implies(&alive_vec[t - 1]));
+    for (e, eff) in &effects {
+        if let Some(effect) = semantics(eff.kind()) {
+            effect.constrain(&ctx, e, eff);
         }
-        // not alive at t -> not alive at t+1 (if t<1000)
-        for t in 0..1000 {
-            solver.assert(&alive_vec[t].not().implies(&alive_vec[t + 1].not()));
-        }
-        // alive by default at time 0
-        solver.assert(&alive_vec[0]);
     }
 
-    for e in &events {
-        let t = event_times.get(&e.name).unwrap();
-
-        for (i, c) in e.characters.iter().enumerate() {
-            let alive_vec = alive_vars.get(&c.name).unwrap();
-
-            // find whether this event kills this character
-            let is_death = e.effects.get(i).map(|eff| matches!(eff, Effect::Death(_))).unwrap_or(false);
-
-
-            for time in 0..=1000 {
-                let time_int = Int::from_i64(time as i64);
-
-                if is_death {
-                    // must be alive strictly *before* the event
-                    solver.assert(&time_int.lt(t).implies(&alive_vec[time]));
-                } else {
-                    // must be alive *at* the event
-                    solver.assert(&time_int._eq(t).implies(&alive_vec[time]));
+    // Constraint 2: Nothing changes twice in the same moment, or which change
+    // came last would be ambiguous
+    for changes in ctx.changed().map(|(_, changes)| changes) {
+        for (i, a) in changes.iter().enumerate() {
+            for b in &changes[i + 1..] {
+                if a.event != b.event {
+                    solver.assert(a.at._eq(&b.at).not());
                 }
             }
         }
     }
 
-
-    // Constraint 5: A character can only die once
-    let mut death_events: HashMap<String, Vec<&Int>> = HashMap::new();
-
-    for e in &events {
-        let t = event_times.get(&e.name).unwrap();
-        for eff in &e.effects {
-            if let Effect::Death(c_name) = eff {
-                death_events.entry(c_name.clone())
-                    .or_default()
-                    .push(t);
+    // Constraint 3 & 4: Everyone taking part is born and alive at the event,
+    // unless one of its effects excuses them, e.g. the victim of a death
+    for e in events {
+        let t = ctx.time(&e.id);
+        for c in &e.characters {
+            let excused = ctx.effects().any(|(d, eff)| {
+                d.id == e.id && semantics(eff.kind()).is_some_and(|s| s.excuses(eff, &c.id))
+            });
+            if !excused {
+                solver.assert(alive_at(&ctx, &c.id, t));
             }
         }
     }
 
-    // For any two death events of the same character, enforce t1 == t2
-    for (c, times) in death_events.clone()   {
-        if times.len() > 1 {
-            for i in 0..times.len() {
-                for j in i + 1..times.len() {
-                    // They must not both be distinct deaths
-                    // Option 1: require equality (same moment of death)
-                    // solver.assert(&times[i]._eq(times[j]));
-
-                    // Option 2 (better): require distinct ordering impossible (t1 != t2)
-                    // i.e. they can't both exist in the same valid model
-                    solver.assert(&times[i]._eq(times[j]));
+    // Conditions a ledger puts on its event, e.g. a faction holding a place
+    for e in events {
+        let t = ctx.time(&e.id);
+        for condition in &e.requires {
+            match condition {
+                Condition::Holds { place, faction } => {
+                    solver.assert(ctx.is_before(Fluent::OwnsPlace, place, &Value::Id(faction.clone()), t));
+                }
+                Condition::Attends { role } => {
+                    // Roles are read from the roster, falling back to what the event carries
+                    let has_role = e.characters.iter().any(|p| {
+                        let known = chars.iter().find(|c| c.id == p.id).unwrap_or(p);
+                        known.role == Some(*role)
+                    });
+                    solver.assert(Bool::from_bool(has_role));
                 }
             }
         }
     }
 
-    for (c, times) in death_events {
-        if times.len() > 1 {
-            // Pick any two death events
-            for i in 0..times.len() {
-                for j in (i + 1)..times.len() {
-                    // It's impossible for both events to "exist" for this character
-                    // because that implies two separate Death(c) effects.
-                    // Enforce: t_i != t_j is impossible if both are valid events.
-                    // So add an UNSAT disjunction.
-                    solver.assert(Bool::not(&times[i].eq(times[j]))); // <- use _ne, not _eq
+    // Constraint 5: A faction that starts with a ruler always has someone alive,
+    // and born, to rule it, or the succession falls into crisis
+    if rules.living_ruler {
+        let mut factions: Vec<&str> = chars.iter().filter(|c| c.role == Some(Role::Ruler)).map(|c| c.faction.as_str()).collect();
+        factions.sort();
+        factions.dedup();
+        for faction in factions {
+            // Anyone crowned may rule too, in or out of the line
+            let mut line = line_of_succession(faction, chars, &rules.relationships);
+            for ruler in ctx.values(Fluent::RulerOf, faction) {
+                if let Value::Id(c) = ruler
+                    && !line.contains(&c)
+                {
+                    line.push(c);
                 }
             }
+            // Claimants are only ever lost to a death, so the start and each
+            // claimant's deaths are the only moments worth checking
+            let mut moments = vec![Int::from_i64(0)];
+            for id in &line {
+                let deaths = ctx.changes(Fluent::Alive, id).iter().filter(|c| c.to == Value::Dead);
+                moments.extend(deaths.map(|c| c.at.clone()));
+            }
+            for at in &moments {
+                let someone: Vec<Bool> = line.iter().map(|id| alive_at(&ctx, id, at)).collect();
+                solver.assert(Bool::or(&someone));
+            }
         }
     }
 
-
-    // Check satisfiability
-    match solver.check() {
-        SatResult::Sat => true,
-        SatResult::Unsat => false,
-        SatResult::Unknown => false, // conservatively return false on unknown
-    }
+    ctx
 }
 
 
@@ -160,13 +189,15 @@ pub fn isPossible(events: Vec<Event>, chars: Vec<Character>) -> bool {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::types::{Event, Character};
+    use std::collections::HashMap;
+    use crate::types::{Event, Character, RelationKind, Relationship, Role};
     use crate::types::Effect::Death;
 
     #[test]
     fn test_simple_sequence() {
         let events = vec![
             Event {
+                id: "e1".to_string(),
                 name: "e1".to_string(),
                 description: "".to_string(),
                 track: 0.0,
@@ -174,10 +205,13 @@ mod tests {
                 start: 0.0,
                 end: 0.0,
                 _type: "normal".to_string(),
-                characters: vec![Character { name: "Alice".to_string(), faction: "A".to_string() }],
+                characters: vec![Character { name: "Alice".to_string(), faction: "A".to_string(), ..Default::default() }],
                 effects: vec![],
+                requires: vec![],
+                place: None,
             },
             Event {
+                id: "e2".to_string(),
                 name: "e2".to_string(),
                 description: "".to_string(),
                 before: vec![],
@@ -185,17 +219,20 @@ mod tests {
                 track: 0.0,
                 end: 0.0,
                 _type: "normal".to_string(),
-                characters: vec![Character { name: "Alice".to_string(), faction: "A".to_string() }],
+                characters: vec![Character { name: "Alice".to_string(), faction: "A".to_string(), ..Default::default() }],
                 effects: vec![],
+                requires: vec![],
+                place: None,
             }
         ];
-        assert!(isPossible(events, vec![Character{name: "Alice".to_string(), faction: "A".to_string()}]));
+        assert!(isPossible(events, vec![Character{name: "Alice".to_string(), faction: "A".to_string(), ..Default::default() }]));
     }
 
     #[test]
     fn test_death_event() {
         let events = vec![
             Event {
+                id: "e1".to_string(),
                 name: "e1".to_string(),
                 description: "".to_string(),
                 before: vec!["e2".to_string()],
@@ -203,10 +240,13 @@ mod tests {
                 end: 0.0,
                 track: 0.0,
                 _type: "combat".to_string(),
-                characters: vec![Character { name: "Bob".to_string(), faction: "B".to_string() }],
+                characters: vec![Character { name: "Bob".to_string(), faction: "B".to_string(), ..Default::default() }],
                 effects: vec![Death("Bob".parse().unwrap())],
+                requires: vec![],
+                place: None,
             },
             Event {
+                id: "e2".to_string(),
                 name: "e2".to_string(),
                 description: "".to_string(),
                 before: vec![],
@@ -214,19 +254,22 @@ mod tests {
                 track: 0.0,
                 end: 0.0,
                 _type: "normal".to_string(),
-                characters: vec![Character { name: "Bob".to_string(), faction: "B".to_string() }],
+                characters: vec![Character { name: "Bob".to_string(), faction: "B".to_string(), ..Default::default() }],
                 effects: vec![],
+                requires: vec![],
+                place: None,
             }
         ];
         // Bob dies in e1, but is in e2 -> impossible
-        assert!(!isPossible(events, vec![Character{name: "Alice".to_string(), faction: "A".to_string()},
-                                         Character{name: "Bob".to_string(), faction: "B".to_string()}]));
+        assert!(!isPossible(events, vec![Character{name: "Alice".to_string(), faction: "A".to_string(), ..Default::default() },
+                                         Character{name: "Bob".to_string(), faction: "B".to_string(), ..Default::default() }]));
     }
 
     #[test]
     fn test_multiple_characters() {
         let events = vec![
             Event {
+                id: "e1".to_string(),
                 name: "e1".to_string(),
                 description: "".to_string(),
                 before: vec!["e2".to_string()],
@@ -235,12 +278,15 @@ mod tests {
                 end: 0.0,
                 _type: "normal".to_string(),
                 characters: vec![
-                    Character { name: "Alice".to_string(), faction: "A".to_string() },
-                    Character { name: "Bob".to_string(), faction: "B".to_string() },
+                    Character { name: "Alice".to_string(), faction: "A".to_string(), ..Default::default() },
+                    Character { name: "Bob".to_string(), faction: "B".to_string(), ..Default::default() },
                 ],
                 effects: vec![],
+                requires: vec![],
+                place: None,
             },
             Event {
+                id: "e2".to_string(),
                 name: "e2".to_string(),
                 description: "".to_string(),
                 before: vec![],
@@ -249,19 +295,22 @@ mod tests {
                 end: 0.0,
                 _type: "normal".to_string(),
                 characters: vec![
-                    Character { name: "Alice".to_string(), faction: "A".to_string() },
+                    Character { name: "Alice".to_string(), faction: "A".to_string(), ..Default::default() },
                 ],
                 effects: vec![],
+                requires: vec![],
+                place: None,
             }
         ];
-        assert!(isPossible(events, vec![Character{name: "Alice".to_string(), faction: "A".to_string()},
-                                         Character{name: "Bob".to_string(), faction: "B".to_string()}]));
+        assert!(isPossible(events, vec![Character{name: "Alice".to_string(), faction: "A".to_string(), ..Default::default() },
+                                         Character{name: "Bob".to_string(), faction: "B".to_string(), ..Default::default() }]));
     }
 
     #[test]
     fn test_double_death_same_character() {
         let events = vec![
             Event {
+                id: "death1".to_string(),
                 name: "death1".to_string(),
                 description: "Bob dies the first time".to_string(),
                 before: vec!["death2".to_string()],
@@ -269,10 +318,13 @@ mod tests {
                 end: 0.0,
                 track: 0.0,
                 _type: "catastrophe".to_string(),
-                characters: vec![Character { name: "Bob".to_string(), faction: "B".to_string() }],
+                characters: vec![Character { name: "Bob".to_string(), faction: "B".to_string(), ..Default::default() }],
                 effects: vec![Death("Bob".to_string())],
+                requires: vec![],
+                place: None,
             },
             Event {
+                id: "death2".to_string(),
                 name: "death2".to_string(),
                 description: "Bob dies again (impossible)".to_string(),
                 before: vec![],
@@ -280,8 +332,10 @@ mod tests {
                 end: 0.0,
                 track: 0.0,
                 _type: "catastrophe".to_string(),
-                characters: vec![Character { name: "Bob".to_string(), faction: "B".to_string() }],
+                characters: vec![Character { name: "Bob".to_string(), faction: "B".to_string(), ..Default::default() }],
                 effects: vec![Death("Bob".to_string())],
+                requires: vec![],
+                place: None,
             }
         ];
 
@@ -289,7 +343,7 @@ mod tests {
         assert!(
             !isPossible(
                 events,
-                vec![Character { name: "Bob".to_string(), faction: "B".to_string() }]
+                vec![Character { name: "Bob".to_string(), faction: "B".to_string(), ..Default::default() }]
             ),
             "A character should not be able to die twice in sequence"
         );
@@ -300,6 +354,7 @@ mod tests {
     fn test_chain_of_deaths() {
         let events = vec![
             Event {
+                id: "e1".to_string(),
                 name: "e1".to_string(),
                 description: "".to_string(),
                 before: vec!["e2".to_string()],
@@ -307,10 +362,13 @@ mod tests {
                 track: 0.0,
                 end: 0.0,
                 _type: "combat".to_string(),
-                characters: vec![Character { name: "Alice".to_string(), faction: "A".to_string() }],
+                characters: vec![Character { name: "Alice".to_string(), faction: "A".to_string(), ..Default::default() }],
                 effects: vec![Death("Alice".to_string())],
+                requires: vec![],
+                place: None,
             },
             Event {
+                id: "e2".to_string(),
                 name: "e2".to_string(),
                 description: "".to_string(),
                 before: vec!["e3".to_string()],
@@ -318,10 +376,13 @@ mod tests {
                 end: 0.0,
                 track: 0.0,
                 _type: "combat".to_string(),
-                characters: vec![Character { name: "Bob".to_string(), faction: "B".to_string() }],
+                characters: vec![Character { name: "Bob".to_string(), faction: "B".to_string(), ..Default::default() }],
                 effects: vec![Death("Bob".to_string())],
+                requires: vec![],
+                place: None,
             },
             Event {
+                id: "e3".to_string(),
                 name: "e3".to_string(),
                 description: "".to_string(),
                 before: vec![],
@@ -329,20 +390,23 @@ mod tests {
                 end: 0.0,
                 track: 0.0,
                 _type: "normal".to_string(),
-                characters: vec![Character { name: "Charlie".to_string(), faction: "C".to_string() }],
+                characters: vec![Character { name: "Charlie".to_string(), faction: "C".to_string(), ..Default::default() }],
                 effects: vec![],
+                requires: vec![],
+                place: None,
             }
         ];
         // No contradictions, should be possible
-        assert!(isPossible(events, vec![Character{name: "Alice".to_string(), faction: "A".to_string()},
-                                         Character{name: "Bob".to_string(), faction: "B".to_string()},
-                                        Character{name: "Charlie".to_string(), faction: "C".to_string()}]));
+        assert!(isPossible(events, vec![Character{name: "Alice".to_string(), faction: "A".to_string(), ..Default::default() },
+                                         Character{name: "Bob".to_string(), faction: "B".to_string(), ..Default::default() },
+                                        Character{name: "Charlie".to_string(), faction: "C".to_string(), ..Default::default() }]));
     }
 
     #[test]
     fn test_impossible_cycle() {
         let events = vec![
             Event {
+                id: "e1".to_string(),
                 name: "e1".to_string(),
                 description: "".to_string(),
                 before: vec!["e2".to_string()],
@@ -350,10 +414,13 @@ mod tests {
                 end: 0.0,
                 track: 0.0,
                 _type: "normal".to_string(),
-                characters: vec![Character { name: "Alice".to_string(), faction: "A".to_string() }],
+                characters: vec![Character { name: "Alice".to_string(), faction: "A".to_string(), ..Default::default() }],
                 effects: vec![],
+                requires: vec![],
+                place: None,
             },
             Event {
+                id: "e2".to_string(),
                 name: "e2".to_string(),
                 description: "".to_string(),
                 before: vec!["e1".to_string()], // cycle
@@ -361,10 +428,754 @@ mod tests {
                 end: 0.0,
                 track: 0.0,
                 _type: "normal".to_string(),
-                characters: vec![Character { name: "Alice".to_string(), faction: "A".to_string() }],
+                characters: vec![Character { name: "Alice".to_string(), faction: "A".to_string(), ..Default::default() }],
                 effects: vec![],
+                requires: vec![],
+                place: None,
             }
         ];
-        assert!(!isPossible(events, vec![Character{name: "Alice".to_string(), faction: "A".to_string()}]));
+        assert!(!isPossible(events, vec![Character{name: "Alice".to_string(), faction: "A".to_string(), ..Default::default() }]));
+    }
+
+    #[test]
+    fn test_transfer_then_holds() {
+        let rules = TimelineRules { ownership: HashMap::from([("Keep".to_string(), "g".to_string())]), ..Default::default() };
+        let events = vec![
+            Event {
+                id: "e1".to_string(),
+                name: "e1".to_string(),
+                description: "".to_string(),
+                before: vec!["e2".to_string()],
+                start: 0.0,
+                track: 0.0,
+                end: 0.0,
+                _type: "conquest".to_string(),
+                characters: vec![],
+                effects: vec![Effect::Transfer { place: "Keep".to_string(), to: "t".to_string() }],
+                requires: vec![],
+                place: None,
+            },
+            Event {
+                id: "e2".to_string(),
+                name: "e2".to_string(),
+                description: "".to_string(),
+                before: vec![],
+                start: 0.0,
+                track: 0.0,
+                end: 0.0,
+                _type: "conquest".to_string(),
+                characters: vec![],
+                effects: vec![],
+                requires: vec![Condition::Holds { place: "Keep".to_string(), faction: "t".to_string() }],
+                place: None,
+            },
+        ];
+        assert!(is_possible_under(events, vec![], &rules));
+    }
+
+    #[test]
+    fn test_holds_before_transfer() {
+        // The trolls need the keep before they have taken it -> impossible
+        let rules = TimelineRules { ownership: HashMap::from([("Keep".to_string(), "g".to_string())]), ..Default::default() };
+        let events = vec![
+            Event {
+                id: "e1".to_string(),
+                name: "e1".to_string(),
+                description: "".to_string(),
+                before: vec!["e2".to_string()],
+                start: 0.0,
+                track: 0.0,
+                end: 0.0,
+                _type: "conquest".to_string(),
+                characters: vec![],
+                effects: vec![],
+                requires: vec![Condition::Holds { place: "Keep".to_string(), faction: "t".to_string() }],
+                place: None,
+            },
+            Event {
+                id: "e2".to_string(),
+                name: "e2".to_string(),
+                description: "".to_string(),
+                before: vec![],
+                start: 0.0,
+                track: 0.0,
+                end: 0.0,
+                _type: "conquest".to_string(),
+                characters: vec![],
+                effects: vec![Effect::Transfer { place: "Keep".to_string(), to: "t".to_string() }],
+                requires: vec![],
+                place: None,
+            },
+        ];
+        assert!(!is_possible_under(events, vec![], &rules));
+    }
+
+    #[test]
+    fn test_possible_owners() {
+        let rules = TimelineRules { ownership: HashMap::from([("Keep".to_string(), "g".to_string())]), ..Default::default() };
+        // e1 and e2 are unordered, so either could be the latest before e3
+        let events = vec![
+            Event {
+                id: "e1".to_string(),
+                name: "e1".to_string(),
+                description: "".to_string(),
+                before: vec!["e3".to_string()],
+                start: 0.0,
+                track: 0.0,
+                end: 0.0,
+                _type: "conquest".to_string(),
+                characters: vec![],
+                effects: vec![Effect::Transfer { place: "Keep".to_string(), to: "t".to_string() }],
+                requires: vec![],
+                place: None,
+            },
+            Event {
+                id: "e2".to_string(),
+                name: "e2".to_string(),
+                description: "".to_string(),
+                before: vec!["e3".to_string()],
+                start: 0.0,
+                track: 0.0,
+                end: 0.0,
+                _type: "conquest".to_string(),
+                characters: vec![],
+                effects: vec![Effect::Transfer { place: "Keep".to_string(), to: "c".to_string() }],
+                requires: vec![],
+                place: None,
+            },
+            Event {
+                id: "e3".to_string(),
+                name: "e3".to_string(),
+                description: "".to_string(),
+                before: vec![],
+                start: 0.0,
+                track: 0.0,
+                end: 0.0,
+                _type: "conquest".to_string(),
+                characters: vec![],
+                effects: vec![],
+                requires: vec![],
+                place: None,
+            },
+        ];
+        let mut owners = possible_owners(&events, &[], &rules, "Keep", "e3");
+        owners.sort();
+        assert_eq!(owners, vec!["c".to_string(), "t".to_string()]);
+
+        // Before either transfer the keep is still the gnomes'
+        let first = vec![Event {
+            id: "e0".to_string(),
+            name: "e0".to_string(),
+            description: "".to_string(),
+            before: vec!["e1".to_string(), "e2".to_string()],
+            start: 0.0,
+            track: 0.0,
+            end: 0.0,
+            _type: "conquest".to_string(),
+            characters: vec![],
+            effects: vec![],
+            requires: vec![],
+            place: None,
+        }];
+        let events: Vec<Event> = first.into_iter().chain(events).collect();
+        assert_eq!(possible_owners(&events, &[], &rules, "Keep", "e0"), vec!["g".to_string()]);
+    }
+
+    #[test]
+    fn test_born_after_taking_part() {
+        // Cara is born in e2 but attends e1, which comes first -> impossible
+        let cara = Character { name: "Cara".to_string(), faction: "A".to_string(), born: Some("e2".to_string()), ..Default::default() };
+        let events = vec![
+            Event {
+                id: "e1".to_string(),
+                name: "e1".to_string(),
+                description: "".to_string(),
+                before: vec!["e2".to_string()],
+                start: 0.0,
+                track: 0.0,
+                end: 0.0,
+                _type: "ceremony".to_string(),
+                characters: vec![cara.clone()],
+                effects: vec![],
+                requires: vec![],
+                place: None,
+            },
+            Event {
+                id: "e2".to_string(),
+                name: "e2".to_string(),
+                description: "".to_string(),
+                before: vec![],
+                start: 0.0,
+                track: 0.0,
+                end: 0.0,
+                _type: "ceremony".to_string(),
+                characters: vec![],
+                effects: vec![],
+                requires: vec![],
+                place: None,
+            },
+        ];
+        assert!(!isPossible(events.clone(), vec![cara.clone()]));
+
+        // The other way round is fine
+        let events = vec![
+            Event {
+                id: "e1".to_string(),
+                name: "e1".to_string(),
+                description: "".to_string(),
+                before: vec!["e2".to_string()],
+                start: 0.0,
+                track: 0.0,
+                end: 0.0,
+                _type: "ceremony".to_string(),
+                characters: vec![],
+                effects: vec![],
+                requires: vec![],
+                place: None,
+            },
+            Event {
+                id: "e2".to_string(),
+                name: "e2".to_string(),
+                description: "".to_string(),
+                before: vec![],
+                start: 0.0,
+                track: 0.0,
+                end: 0.0,
+                _type: "ceremony".to_string(),
+                characters: vec![cara.clone()],
+                effects: vec![],
+                requires: vec![],
+                place: None,
+            },
+        ];
+        let cara = Character { born: Some("e1".to_string()), ..cara };
+        assert!(isPossible(events, vec![cara]));
+    }
+
+    #[test]
+    fn test_recorded_death() {
+        // Bob's death is recorded on him rather than as an effect of e1
+        let bob = Character { name: "Bob".to_string(), faction: "B".to_string(), died: Some("e1".to_string()), ..Default::default() };
+        let events = vec![
+            Event {
+                id: "e1".to_string(),
+                name: "e1".to_string(),
+                description: "".to_string(),
+                before: vec!["e2".to_string()],
+                start: 0.0,
+                track: 0.0,
+                end: 0.0,
+                _type: "ceremony".to_string(),
+                characters: vec![],
+                effects: vec![],
+                requires: vec![],
+                place: None,
+            },
+            Event {
+                id: "e2".to_string(),
+                name: "e2".to_string(),
+                description: "".to_string(),
+                before: vec![],
+                start: 0.0,
+                track: 0.0,
+                end: 0.0,
+                _type: "ceremony".to_string(),
+                characters: vec![bob.clone()],
+                effects: vec![],
+                requires: vec![],
+                place: None,
+            },
+        ];
+        assert!(!isPossible(events, vec![bob]));
+    }
+
+    #[test]
+    fn test_priest_officiates() {
+        let priest = Character { name: "Abbot".to_string(), faction: "A".to_string(), role: Some(Role::Priest), ..Default::default() };
+        let knight = Character { name: "Sir Tom".to_string(), faction: "A".to_string(), role: Some(Role::Knight), ..Default::default() };
+        let needs_priest = vec![Condition::Attends { role: Role::Priest }];
+
+        let events = vec![Event {
+            id: "e1".to_string(),
+            name: "e1".to_string(),
+            description: "".to_string(),
+            before: vec![],
+            start: 0.0,
+            track: 0.0,
+            end: 0.0,
+            _type: "ceremony".to_string(),
+            characters: vec![knight.clone()],
+            effects: vec![],
+            requires: needs_priest.clone(),
+            place: None,
+        }];
+        assert!(!isPossible(events, vec![priest.clone(), knight.clone()]));
+
+        let events = vec![Event {
+            id: "e1".to_string(),
+            name: "e1".to_string(),
+            description: "".to_string(),
+            before: vec![],
+            start: 0.0,
+            track: 0.0,
+            end: 0.0,
+            _type: "ceremony".to_string(),
+            characters: vec![priest.clone(), knight.clone()],
+            effects: vec![],
+            requires: needs_priest,
+            place: None,
+        }];
+        assert!(isPossible(events, vec![priest, knight]));
+    }
+
+    #[test]
+    fn test_namesakes_kept_apart() {
+        // Two Ashes; the gnome dies in e1 but the troll can still attend e2
+        let gnome = Character { id: "ash-g".to_string(), name: "Ash".to_string(), faction: "g".to_string(), ..Default::default() };
+        let troll = Character { id: "ash-t".to_string(), name: "Ash".to_string(), faction: "t".to_string(), ..Default::default() };
+        let events = vec![
+            Event {
+                id: "e1".to_string(),
+                name: "Cave-in".to_string(),
+                description: "".to_string(),
+                before: vec!["e2".to_string()],
+                start: 0.0,
+                track: 0.0,
+                end: 0.0,
+                _type: "catastrophe".to_string(),
+                characters: vec![gnome.clone()],
+                effects: vec![Death("ash-g".to_string())],
+                requires: vec![],
+                place: None,
+            },
+            Event {
+                id: "e2".to_string(),
+                name: "e2".to_string(),
+                description: "".to_string(),
+                before: vec![],
+                start: 0.0,
+                track: 0.0,
+                end: 0.0,
+                _type: "ceremony".to_string(),
+                characters: vec![troll.clone()],
+                effects: vec![],
+                requires: vec![],
+                place: None,
+            },
+        ];
+        assert!(isPossible(events, vec![gnome, troll]));
+    }
+
+    #[test]
+    fn test_succession_crisis() {
+        let king = Character { id: "king".to_string(), name: "Old King".to_string(), faction: "g".to_string(), role: Some(Role::Ruler), ..Default::default() };
+        let prince = Character { id: "prince".to_string(), name: "Prince".to_string(), faction: "g".to_string(), role: Some(Role::Heir), ..Default::default() };
+        let rules = TimelineRules { living_ruler: true, ..Default::default() };
+
+        // The prince takes the throne when the king dies
+        let events = vec![Event {
+            id: "e1".to_string(),
+            name: "e1".to_string(),
+            description: "".to_string(),
+            before: vec![],
+            start: 0.0,
+            track: 0.0,
+            end: 0.0,
+            _type: "catastrophe".to_string(),
+            characters: vec![king.clone()],
+            effects: vec![Death("king".to_string())],
+            requires: vec![],
+            place: None,
+        }];
+        assert!(is_possible_under(events, vec![king.clone(), prince.clone()], &rules));
+
+        // Nobody is left once the prince dies too
+        let events = vec![
+            Event {
+                id: "e1".to_string(),
+                name: "e1".to_string(),
+                description: "".to_string(),
+                before: vec!["e2".to_string()],
+                start: 0.0,
+                track: 0.0,
+                end: 0.0,
+                _type: "catastrophe".to_string(),
+                characters: vec![king.clone()],
+                effects: vec![Death("king".to_string())],
+                requires: vec![],
+                place: None,
+            },
+            Event {
+                id: "e2".to_string(),
+                name: "e2".to_string(),
+                description: "".to_string(),
+                before: vec![],
+                start: 0.0,
+                track: 0.0,
+                end: 0.0,
+                _type: "catastrophe".to_string(),
+                characters: vec![prince.clone()],
+                effects: vec![Death("prince".to_string())],
+                requires: vec![],
+                place: None,
+            },
+        ];
+        assert!(!is_possible_under(events.clone(), vec![king.clone(), prince.clone()], &rules));
+        assert!(isPossible(events, vec![king, prince]));
+    }
+
+    #[test]
+    fn test_birth_event() {
+        let cub = Character { id: "cub".to_string(), name: "Cub".to_string(), faction: "t".to_string(), ..Default::default() };
+        let events = vec![
+            Event {
+                id: "e1".to_string(),
+                name: "e1".to_string(),
+                description: "".to_string(),
+                before: vec!["e2".to_string()],
+                start: 0.0,
+                end: 0.0,
+                track: 0.0,
+                _type: "normal".to_string(),
+                characters: vec![cub.clone()],
+                effects: vec![],
+                requires: vec![],
+                place: None,
+            },
+            Event {
+                id: "e2".to_string(),
+                name: "e2".to_string(),
+                description: "".to_string(),
+                before: vec![],
+                start: 0.0,
+                end: 0.0,
+                track: 0.0,
+                _type: "normal".to_string(),
+                characters: vec![],
+                effects: vec![Effect::Birth("cub".to_string())],
+                requires: vec![],
+                place: None,
+            },
+        ];
+        // Cub attends e1 but is only born in e2 -> impossible
+        assert!(!isPossible(events.clone(), vec![cub.clone()]));
+
+        // Born in e1 and attending e2 is fine
+        let mut swapped = events.clone();
+        swapped[0].effects = vec![Effect::Birth("cub".to_string())];
+        swapped[1].effects = vec![];
+        assert!(isPossible(swapped, vec![cub.clone()]));
+
+        // Nobody is born twice
+        let mut twice = events;
+        twice[0].characters = vec![];
+        twice[0].effects = vec![Effect::Birth("cub".to_string())];
+        assert!(!isPossible(twice, vec![cub]));
+    }
+
+    #[test]
+    fn test_marriage_event() {
+        let events = vec![
+            Event {
+                id: "e1".to_string(),
+                name: "e1".to_string(),
+                description: "".to_string(),
+                before: vec!["e2".to_string()],
+                start: 0.0,
+                end: 0.0,
+                track: 0.0,
+                _type: "ceremony".to_string(),
+                characters: vec![],
+                effects: vec![Effect::Marriage("ann".to_string(), "bo".to_string())],
+                requires: vec![],
+                place: None,
+            },
+            Event {
+                id: "e2".to_string(),
+                name: "e2".to_string(),
+                description: "".to_string(),
+                before: vec![],
+                start: 0.0,
+                end: 0.0,
+                track: 0.0,
+                _type: "ceremony".to_string(),
+                characters: vec![],
+                effects: vec![Effect::Marriage("ann".to_string(), "cy".to_string())],
+                requires: vec![],
+                place: None,
+            },
+        ];
+        let roster = vec![
+            Character { id: "ann".to_string(), name: "Ann".to_string(), faction: "g".to_string(), ..Default::default() },
+            Character { id: "bo".to_string(), name: "Bo".to_string(), faction: "g".to_string(), ..Default::default() },
+            Character { id: "cy".to_string(), name: "Cy".to_string(), faction: "t".to_string(), ..Default::default() },
+        ];
+        // Ann weds Cy while Bo, her first husband, still lives -> impossible
+        assert!(!isPossible(events.clone(), roster.clone()));
+
+        // A widow may remarry, even in the event that makes her one
+        let mut widowed = events.clone();
+        widowed[1].effects.insert(0, Death("bo".to_string()));
+        assert!(isPossible(widowed, roster.clone()));
+
+        // Marriages the rules start with count too
+        let rules = TimelineRules {
+            relationships: vec![
+                Relationship { from: "ann".into(), to: "bo".into(), kind: RelationKind::Spouse },
+                Relationship { from: "bo".into(), to: "ann".into(), kind: RelationKind::Spouse },
+            ],
+            ..Default::default()
+        };
+        assert!(!is_possible_under(events[1..].to_vec(), roster.clone(), &rules));
+
+        // A spouse given one way only binds both partners
+        for (from, to) in [("ann", "bo"), ("bo", "ann")] {
+            let rules = TimelineRules {
+                relationships: vec![Relationship { from: from.into(), to: to.into(), kind: RelationKind::Spouse }],
+                ..Default::default()
+            };
+            assert!(!is_possible_under(events[1..].to_vec(), roster.clone(), &rules));
+        }
+    }
+
+    #[test]
+    fn test_coronation_event() {
+        let events = vec![
+            Event {
+                id: "e1".to_string(),
+                name: "e1".to_string(),
+                description: "".to_string(),
+                before: vec!["e2".to_string()],
+                start: 0.0,
+                end: 0.0,
+                track: 0.0,
+                _type: "combat".to_string(),
+                characters: vec![],
+                effects: vec![Death("king".to_string())],
+                requires: vec![],
+                place: None,
+            },
+            Event {
+                id: "e2".to_string(),
+                name: "e2".to_string(),
+                description: "".to_string(),
+                before: vec![],
+                start: 0.0,
+                end: 0.0,
+                track: 0.0,
+                _type: "ceremony".to_string(),
+                characters: vec![],
+                effects: vec![Effect::Coronation("prince".to_string())],
+                requires: vec![],
+                place: None,
+            },
+        ];
+        let roster = vec![
+            Character { id: "king".to_string(), name: "King".to_string(), faction: "g".to_string(), role: Some(Role::Ruler), ..Default::default() },
+            Character { id: "prince".to_string(), name: "Prince".to_string(), faction: "g".to_string(), role: Some(Role::Heir), ..Default::default() },
+        ];
+        assert!(isPossible(events.clone(), roster.clone()));
+
+        // The old king still lives -> impossible
+        assert!(!isPossible(events[1..].to_vec(), roster.clone()));
+
+        // Nor can the dead be crowned
+        let mut both_dead = events;
+        both_dead[0].effects.push(Death("prince".to_string()));
+        assert!(!isPossible(both_dead, roster));
+    }
+
+    #[test]
+    fn test_exile_event() {
+        let knight = Character { id: "knight".to_string(), name: "Knight".to_string(), faction: "g".to_string(), role: Some(Role::Knight), ..Default::default() };
+        let events = vec![
+            Event {
+                id: "e1".to_string(),
+                name: "e1".to_string(),
+                description: "".to_string(),
+                before: vec!["e2".to_string()],
+                start: 0.0,
+                end: 0.0,
+                track: 0.0,
+                _type: "banishment".to_string(),
+                characters: vec![knight.clone()],
+                effects: vec![Effect::Exile("knight".to_string())],
+                requires: vec![],
+                place: None,
+            },
+            Event {
+                id: "e2".to_string(),
+                name: "e2".to_string(),
+                description: "".to_string(),
+                before: vec![],
+                start: 0.0,
+                end: 0.0,
+                track: 0.0,
+                _type: "normal".to_string(),
+                characters: vec![knight.clone()],
+                effects: vec![],
+                requires: vec![],
+                place: Some("Keep".to_string()),
+            },
+        ];
+        let rules = TimelineRules {
+            ownership: HashMap::from([("Keep".to_string(), "g".to_string()), ("Fort".to_string(), "t".to_string())]),
+            ..Default::default()
+        };
+        // Banished, yet back at the gnomes' keep -> impossible
+        assert!(!is_possible_under(events.clone(), vec![knight.clone()], &rules));
+
+        // Abroad at the trolls' fort is fine
+        let mut abroad = events.clone();
+        abroad[1].place = Some("Fort".to_string());
+        assert!(is_possible_under(abroad, vec![knight.clone()], &rules));
+
+        // So is coming home in the same event he returns in
+        let mut returned = events.clone();
+        returned[1].effects = vec![Effect::Return("knight".to_string())];
+        assert!(is_possible_under(returned, vec![knight.clone()], &rules));
+
+        // Only an exile can return
+        let mut never_left = events;
+        never_left[0].effects = vec![Effect::Return("knight".to_string())];
+        assert!(!is_possible_under(never_left, vec![knight], &rules));
+    }
+
+    #[test]
+    fn test_injury_event() {
+        let bob = Character { id: "bob".to_string(), name: "Bob".to_string(), faction: "b".to_string(), ..Default::default() };
+        let events = vec![
+            Event {
+                id: "e1".to_string(),
+                name: "e1".to_string(),
+                description: "".to_string(),
+                before: vec!["e2".to_string()],
+                start: 0.0,
+                end: 0.0,
+                track: 0.0,
+                _type: "tournament".to_string(),
+                characters: vec![bob.clone()],
+                effects: vec![Effect::Injury("bob".to_string())],
+                requires: vec![],
+                place: None,
+            },
+            Event {
+                id: "e2".to_string(),
+                name: "e2".to_string(),
+                description: "".to_string(),
+                before: vec![],
+                start: 0.0,
+                end: 0.0,
+                track: 0.0,
+                _type: "normal".to_string(),
+                characters: vec![bob.clone()],
+                effects: vec![],
+                requires: vec![],
+                place: None,
+            },
+        ];
+        assert!(isPossible(events.clone(), vec![bob.clone()]));
+
+        // Bob can't both survive e1 and die in it
+        let mut fatal = events.clone();
+        fatal[0].effects.push(Death("bob".to_string()));
+        assert!(!isPossible(fatal, vec![bob.clone()]));
+
+        // Nor be hurt once he is dead
+        let mut too_late = events;
+        too_late[0].effects = vec![Death("bob".to_string())];
+        too_late[1].characters = vec![];
+        too_late[1].effects = vec![Effect::Injury("bob".to_string())];
+        assert!(!isPossible(too_late, vec![bob]));
+    }
+
+    #[test]
+    fn test_resurrection_event() {
+        let bob = Character { id: "bob".to_string(), name: "Bob".to_string(), faction: "b".to_string(), ..Default::default() };
+        let events = vec![
+            Event {
+                id: "e1".to_string(),
+                name: "e1".to_string(),
+                description: "".to_string(),
+                before: vec!["e2".to_string()],
+                start: 0.0,
+                end: 0.0,
+                track: 0.0,
+                _type: "combat".to_string(),
+                characters: vec![bob.clone()],
+                effects: vec![Death("bob".to_string())],
+                requires: vec![],
+                place: None,
+            },
+            Event {
+                id: "e2".to_string(),
+                name: "e2".to_string(),
+                description: "".to_string(),
+                before: vec![],
+                start: 0.0,
+                end: 0.0,
+                track: 0.0,
+                _type: "miracle".to_string(),
+                characters: vec![bob.clone()],
+                effects: vec![Effect::Resurrection("bob".to_string())],
+                requires: vec![],
+                place: None,
+            },
+        ];
+        // Bob dies in e1 and rises in e2
+        assert!(isPossible(events.clone(), vec![bob.clone()]));
+
+        // Only the dead can rise
+        assert!(!isPossible(events[1..].to_vec(), vec![bob]));
+    }
+
+    #[test]
+    fn test_no_two_changes_at_once() {
+        let base = Event {
+            id: "e1".to_string(),
+            name: "e1".to_string(),
+            description: "".to_string(),
+            before: vec![],
+            start: 0.0,
+            end: 0.0,
+            track: 0.0,
+            _type: "normal".to_string(),
+            characters: vec![],
+            effects: vec![],
+            requires: vec![],
+            place: None,
+        };
+        let roster = vec![
+            Character { id: "king".to_string(), name: "King".to_string(), faction: "g".to_string(), role: Some(Role::Ruler), ..Default::default() },
+            Character { id: "ann".to_string(), name: "Ann".to_string(), faction: "g".to_string(), ..Default::default() },
+            Character { id: "bo".to_string(), name: "Bo".to_string(), faction: "g".to_string(), ..Default::default() },
+            Character { id: "cy".to_string(), name: "Cy".to_string(), faction: "g".to_string(), ..Default::default() },
+        ];
+        // Two unordered events, each of which is fine on its own but would
+        // only both be fine if they happened at the same moment
+        let pair = |first: Effect, second: Effect| {
+            vec![
+                Event { effects: vec![first], ..base.clone() },
+                Event { id: "e2".to_string(), name: "e2".to_string(), effects: vec![second], ..base.clone() },
+            ]
+        };
+        let wed = |a: &str, b: &str| Effect::Marriage(a.to_string(), b.to_string());
+
+        // Married: Ann can't wed Bo and Cy at once, and whoever she weds first lives on
+        let weddings = pair(wed("ann", "bo"), wed("ann", "cy"));
+        assert!(isPossible(weddings[1..].to_vec(), roster.clone()));
+        assert!(!isPossible(weddings, roster.clone()));
+
+        // RulerOf: once the king is dead, only one of Ann and Bo is crowned first,
+        // and the other can't be crowned while they live
+        let mut crownings = pair(Effect::Coronation("ann".to_string()), Effect::Coronation("bo".to_string()));
+        crownings.push(Event { id: "e0".to_string(), name: "e0".to_string(), before: vec!["e1".to_string(), "e2".to_string()], effects: vec![Death("king".to_string())], ..base.clone() });
+        assert!(isPossible(crownings[1..].to_vec(), roster.clone()));
+        assert!(!isPossible(crownings, roster.clone()));
+
+        // Exiled: Ann can't be banished twice over in the same moment
+        let banishments = pair(Effect::Exile("ann".to_string()), Effect::Exile("ann".to_string()));
+        assert!(isPossible(banishments[..1].to_vec(), roster.clone()));
+        assert!(!isPossible(banishments, roster));
     }
 }
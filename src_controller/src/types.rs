@@ -1,18 +1,52 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
 
 use std::hash::{Hash, Hasher};
 use serde::{Deserialize, Serialize};
+use crate::utils::factions::factions;
+use crate::utils::projection::Projection;
+
+/// Medieval role a place plays in the kingdom, picked from its real-world categories
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Archetype {
+    Castle,
+    Cathedral,
+    Market,
+    Tavern,
+    Tower,
+    #[default]
+    Village,
+}
+
+/// Metadata kept from the map provider
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct PlaceInfo {
+    #[serde(default)]
+    pub types: Vec<String>,
+    #[serde(default)]
+    pub rating: Option<f64>,
+    #[serde(default)]
+    pub vicinity: Option<String>,
+    /// Real-world name, kept once the place has been given a medieval one
+    #[serde(default)]
+    pub original_name: Option<String>,
+}
 
 #[derive(Clone)]
 pub struct Place {
+    /// Stable id used wherever something refers to the place; `name` is for display only
+    pub id: String,
     pub name: String,
     pub location: (f64, f64),
+    pub archetype: Archetype,
+    pub info: PlaceInfo,
 }
 
+/// Places are the same place when they share an id, whatever they are called
 impl PartialEq for Place {
     fn eq(&self, other: &Self) -> bool {
-        self.name == other.name && self.location == other.location
+        self.id == other.id
     }
 }
 
@@ -20,9 +54,7 @@ impl Eq for Place {}
 
 impl Hash for Place {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        self.name.hash(state);
-        self.location.0.to_bits().hash(state); // f64 cannot be hashed directly
-        self.location.1.to_bits().hash(state);
+        self.id.hash(state);
     }
 }
 
@@ -33,30 +65,246 @@ impl fmt::Display for Place {
     }
 }
 
+/// Parameters for acquiring a map from the provider, read from the `INIT_MAP` request.
+/// Any field left out of the request keeps its default.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MapParams {
+    /// Number of places to find
+    pub count: usize,
+    /// Search radius around the city centre, in metres
+    pub radius_m: f64,
+    /// Minimum distance between any two chosen places, in metres
+    pub min_spacing_m: f64,
+    /// Provider place types to search for; results from each are merged
+    pub place_types: Vec<String>,
+}
+
+impl Default for MapParams {
+    fn default() -> Self {
+        MapParams {
+            count: 10,
+            radius_m: 1609.0,
+            min_spacing_m: 200.0,
+            place_types: vec!["tourist_attraction".to_string()],
+        }
+    }
+}
+
+/// How places are shared out between centroids when clustering
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Assignment {
+    /// Each place takes the closest centroid that still has room, in map order
+    #[default]
+    Greedy,
+    /// Least total distance under the same capacity limits
+    Optimal,
+}
+
+/// How distance between places is measured when clustering
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Metric {
+    /// Straight line in game space
+    #[default]
+    Euclidean,
+    /// Shortest path along the roads, or a straight line where none joins them
+    Road,
+}
+
+/// Parameters for dividing the map between factions, read from the `INIT_MAP` request.
+/// Any field left out of the request keeps its default.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClusterParams {
+    /// Number of factions, taken from the front of the registry
+    pub faction_count: usize,
+    pub assignment: Assignment,
+    pub metric: Metric,
+    /// Reject the map when a fairness ratio in `ClusterMetrics` falls below this
+    pub min_balance: Option<f64>,
+}
+
+impl Default for ClusterParams {
+    fn default() -> Self {
+        ClusterParams {
+            faction_count: factions().iter().count(),
+            assignment: Assignment::default(),
+            metric: Metric::default(),
+            min_balance: None,
+        }
+    }
+}
+
+/// Parameters for generating character names, read from the `INIT_MAP` request.
+/// Any field left out of the request keeps its default.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NameParams {
+    /// Letters of context the Markov chain looks back over
+    pub order: usize,
+    pub min_length: usize,
+    pub max_length: usize,
+    /// Reject names that spell a common word or one in the server's word list
+    pub avoid_real_words: bool,
+    /// Extra words never to use as names, whatever `avoid_real_words` says
+    pub blocked_words: Vec<String>,
+}
+
+impl Default for NameParams {
+    fn default() -> Self {
+        NameParams {
+            order: 2,
+            min_length: 3,
+            max_length: 14,
+            avoid_real_words: true,
+            blocked_words: vec![],
+        }
+    }
+}
+
+/// Parameters for generating each faction's starting characters, read from the
+/// `INIT_MAP` request. Any field left out of the request keeps its default.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RosterParams {
+    /// Characters per faction
+    pub roster_size: usize,
+    /// Least number of each role per faction, e.g. `{"ruler": 1, "knight": 2}`.
+    /// Replaces the defaults, and must include exactly one ruler. Roles not
+    /// listed get none reserved; slots left over go to knights, merchants and priests.
+    pub role_quotas: BTreeMap<Role, usize>,
+    #[serde(flatten)]
+    pub names: NameParams,
+}
+
+impl Default for RosterParams {
+    fn default() -> Self {
+        RosterParams {
+            roster_size: 4,
+            role_quotas: BTreeMap::from([(Role::Ruler, 1), (Role::Heir, 1), (Role::Knight, 1), (Role::Priest, 1)]),
+            names: NameParams::default(),
+        }
+    }
+}
+
+/// A road between two places, keyed by their index in `Map.locations`.
+/// `path` is the polyline in game space; it is carried separately from the
+/// edge metadata when serialised so the client still sees a plain list of routes.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RoadEdge {
+    pub from: usize,
+    pub to: usize,
+    pub length_m: f64,
+    pub travel_time_s: f64,
+    /// Inter-city road joining two regions of a multi-city realm
+    #[serde(default)]
+    pub highway: bool,
+    #[serde(skip)]
+    pub path: Vec<(f64, f64)>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct RoadNetwork {
+    pub edges: Vec<RoadEdge>,
+}
+
+impl RoadNetwork {
+    /// Iterate over the roads touching `place`, yielding the place at the other end
+    pub fn neighbours(&self, place: usize) -> impl Iterator<Item = (usize, &RoadEdge)> {
+        self.edges.iter().filter_map(move |e| {
+            if e.from == place {
+                Some((e.to, e))
+            } else if e.to == place {
+                Some((e.from, e))
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Road polylines in edge order
+    pub fn routes(&self) -> Vec<Vec<(f64, f64)>> {
+        self.edges.iter().map(|e| e.path.clone()).collect()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FeatureKind {
+    River,
+    Lake,
+    Forest,
+    Coastline,
+}
+
+impl FeatureKind {
+    /// Lakes and forests are areas; rivers and coastline are lines
+    pub fn is_area(&self) -> bool {
+        matches!(self, FeatureKind::Lake | FeatureKind::Forest)
+    }
+}
+
+/// A terrain or water feature in game space, already clipped to the unit circle
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Feature {
+    pub kind: FeatureKind,
+    #[serde(default)]
+    pub name: Option<String>,
+    pub geometry: Vec<(f64, f64)>,
+}
+
+/// The places that came from one source city of a multi-city realm
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Region {
+    pub name: String,
+    /// Indices into `Map.locations`
+    pub places: Vec<usize>,
+}
+
 #[derive(Clone)]
 pub struct Map {
     pub locations: Vec<Place>,
-    pub routes: Vec<Vec<(f64, f64)>>
+    pub roads: RoadNetwork,
+    /// Maps real coordinates into game space and back; `None` for maps saved
+    /// before the projection was recorded
+    pub projection: Option<Projection>,
+    /// Optional terrain layers (rivers, lakes, forests, coastline)
+    pub features: Vec<Feature>,
+    /// Source cities the places were gathered from; empty for maps saved before
+    /// regions were recorded
+    pub regions: Vec<Region>,
 }
 
 impl fmt::Display for Map {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         writeln!(f, "Locations:")?;
         for (i, place) in self.locations.iter().enumerate() {
-            writeln!(f, "  {}. {}", i + 1, place)?;
+            match self.projection {
+                Some(projection) => {
+                    let (lat, lng) = projection.inverse(place.location);
+                    writeln!(f, "  {}. {} [lat {:.5}, lng {:.5}]", i + 1, place, lat, lng)?;
+                }
+                None => writeln!(f, "  {}. {}", i + 1, place)?,
+            }
         }
-        writeln!(f, "Routes: {}", self.routes.len())?;
-        for (i, route) in self.routes.iter().enumerate() {
+        writeln!(f, "Roads: {}", self.roads.edges.len())?;
+        for (i, road) in self.roads.edges.iter().enumerate() {
             // Take first 5 points or fewer
-            let first_points: Vec<String> = route.iter()
+            let first_points: Vec<String> = road.path.iter()
                 .take(5)
                 .map(|(x, y)| format!("({:.5}, {:.5})", x, y))
                 .collect();
             writeln!(
                 f,
-                "  Route {} ({} points, first 5: [{}])",
+                "  Road {} ({} -> {}, {:.0} m, {:.0} s, {} points, first 5: [{}])",
                 i + 1,
-                route.len(),
+                self.locations.get(road.from).map_or("?", |p| p.name.as_str()),
+                self.locations.get(road.to).map_or("?", |p| p.name.as_str()),
+                road.length_m,
+                road.travel_time_s,
+                road.path.len(),
                 first_points.join(", ")
             )?;
         }
@@ -67,6 +315,11 @@ impl fmt::Display for Map {
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct Event {
+    /// Stable id used by `before` and everything else referring to the event.
+    /// Events sent without one get one from `ids::upgrade_legacy`.
+    #[serde(default)]
+    pub id: String,
+
     #[serde(default)]
     pub name: String,
 
@@ -91,6 +344,15 @@ pub struct Event {
     #[serde(default)]
     pub effects: Vec<Effect>,
 
+    /// Conditions that must hold as the event begins
+    #[serde(default)]
+    pub requires: Vec<Condition>,
+
+    /// Id of the place the event happens at. Events without one are taken to
+    /// be in every faction's home realm.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub place: Option<String>,
+
     #[serde(default)]
     pub track: f32,
 }
@@ -98,23 +360,266 @@ pub struct Event {
 
 pub type Ownership = HashMap<Place, String>;
 
+/// Something an event changes. Characters are referred to by id.
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub enum Effect {
-    Death(String),                       // Death carries a single String
+    Death(String),                       // Death carries the id of the character who dies
+    /// The place with id `place` passes to the faction `to`, e.g. after a siege
+    Transfer { place: String, to: String },
+    /// The character is born, as if the event were their `born`
+    Birth(String),
+    /// The two characters wed; neither may marry again while the other lives
+    Marriage(String, String),
+    /// The character is crowned ruler of their faction, once the last ruler is dead
+    Coronation(String),
+    /// The character is banished from places their faction holds until they return
+    Exile(String),
+    /// An exiled character comes home
+    Return(String),
+    /// The character is hurt but survives it, so is alive as it happens
+    Injury(String),
+    /// A dead character lives again
+    Resurrection(String),
+}
+
+impl Effect {
+    /// Ids of the characters the effect acts on
+    pub fn characters(&self) -> Vec<&String> {
+        match self {
+            Effect::Transfer { .. } => vec![],
+            Effect::Marriage(a, b) => vec![a, b],
+            Effect::Death(c)
+            | Effect::Birth(c)
+            | Effect::Coronation(c)
+            | Effect::Exile(c)
+            | Effect::Return(c)
+            | Effect::Injury(c)
+            | Effect::Resurrection(c) => vec![c],
+        }
+    }
+
+    pub fn characters_mut(&mut self) -> Vec<&mut String> {
+        match self {
+            Effect::Transfer { .. } => vec![],
+            Effect::Marriage(a, b) => vec![a, b],
+            Effect::Death(c)
+            | Effect::Birth(c)
+            | Effect::Coronation(c)
+            | Effect::Exile(c)
+            | Effect::Return(c)
+            | Effect::Injury(c)
+            | Effect::Resurrection(c) => vec![c],
+        }
+    }
+
+    pub fn kind(&self) -> EffectKind {
+        match self {
+            Effect::Death(_) => EffectKind::Death,
+            Effect::Transfer { .. } => EffectKind::Transfer,
+            Effect::Birth(_) => EffectKind::Birth,
+            Effect::Marriage(..) => EffectKind::Marriage,
+            Effect::Coronation(_) => EffectKind::Coronation,
+            Effect::Exile(_) => EffectKind::Exile,
+            Effect::Return(_) => EffectKind::Return,
+            Effect::Injury(_) => EffectKind::Injury,
+            Effect::Resurrection(_) => EffectKind::Resurrection,
+        }
+    }
+}
+
+/// The variants of `Effect`, as named in `event_types.json`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EffectKind {
+    Death,
+    Transfer,
+    Birth,
+    Marriage,
+    Coronation,
+    Exile,
+    Return,
+    Injury,
+    Resurrection,
+}
+
+/// Something a ledger needs to be true when its event takes place
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub enum Condition {
+    /// `faction` holds the place with id `place` as the event begins
+    Holds { place: String, faction: String },
+    /// Someone with `role` takes part, e.g. a priest to officiate a ceremony
+    Attends { role: Role },
+}
+
+
+/// How a faction renames the places it holds
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct NamingStyle {
+    /// Templates such as `"Old {}"`, where `{}` is the place name
+    #[serde(default)]
+    pub templates: Vec<String>,
+    /// Short description of the faction's voice, given to the LLM
+    #[serde(default)]
+    pub style: String,
+}
+
+/// Places a faction would rather hold
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TerritoryPreference {
+    #[serde(default)]
+    pub archetypes: Vec<Archetype>,
+}
+
+/// One of the peoples competing for the map, as listed in `factions.json`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Faction {
+    /// Short code used in ownership, `names.json` and the client
+    pub id: String,
+    pub name: String,
+    /// `[r, g, b]`
+    pub colour: (u8, u8, u8),
+    /// Client sprite shown for the faction
+    pub emblem: String,
+    /// Client font for the faction's labels; the client's own default when unset
+    #[serde(default)]
+    pub font: Option<String>,
+    #[serde(default)]
+    pub naming: NamingStyle,
+    #[serde(default)]
+    pub territory: TerritoryPreference,
 }
 
+/// A character's place in their faction
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Ruler,
+    Heir,
+    Knight,
+    Priest,
+    Merchant,
+}
+
+/// Whether an event's participants come from one faction or several
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FactionMix {
+    #[default]
+    Same,
+    /// Spread across as many factions as the count allows
+    Mixed,
+}
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Who takes part in an event of a given type
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ParticipantRule {
+    pub min: usize,
+    pub max: usize,
+    pub factions: FactionMix,
+    /// Roles someone must take part as, e.g. a priest to officiate
+    pub roles: Vec<Role>,
+    /// Roles picked first while there is room, e.g. knights to lead a siege
+    pub favoured: Vec<Role>,
+}
+
+impl Default for ParticipantRule {
+    fn default() -> Self {
+        ParticipantRule { min: 0, max: 3, factions: FactionMix::Same, roles: vec![], favoured: vec![] }
+    }
+}
+
+/// A kind of event `gen_event` can create, as listed in `event_types.json`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EventType {
+    pub id: String,
+    /// Relative chance of being picked
+    #[serde(default = "default_weight")]
+    pub weight: f64,
+    /// Effects the event may have; each not in `required_effects` happens with `effect_chance`
+    #[serde(default)]
+    pub allowed_effects: Vec<EffectKind>,
+    /// Effects the event always has. The type is never picked when one can't be made.
+    #[serde(default)]
+    pub required_effects: Vec<EffectKind>,
+    #[serde(default)]
+    pub effect_chance: f64,
+    #[serde(default)]
+    pub participants: ParticipantRule,
+    /// Kind of place the event would rather happen at
+    #[serde(default)]
+    pub archetype: Option<Archetype>,
+    /// Words and themes given to the LLM when naming the event
+    #[serde(default)]
+    pub flavour: Vec<String>,
+}
+
+fn default_weight() -> f64 {
+    1.0
+}
+
+/// Everything beyond `name` and `faction` is optional and left out when unset,
+/// so saves from before these fields existed load unchanged
+#[derive(Debug, Default, Serialize, Deserialize)]
 #[derive(Clone)]
 pub struct Character {
+    /// Stable id used by effects and other events; two characters may share a name
+    #[serde(default)]
+    pub id: String,
     pub(crate) name: String,
     pub(crate) faction: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<Role>,
+    /// Id of the place the character lives in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub home: Option<String>,
+    /// Id of the event the character is born in; they take part in nothing before it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub born: Option<String>,
+    /// Id of the event the character dies in, as if it had a `Death` effect for them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub died: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub traits: Vec<String>,
+}
+
+/// How one character stands to another; see `Relationship`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RelationKind {
+    Parent,
+    Spouse,
+    /// Named to inherit from the other character
+    Heir,
+    Rival,
+    SwornTo,
+}
+
+/// `from` is the `kind` of `to`: `from` is a parent of `to`, heir to `to`,
+/// sworn to `to`, and so on. Both ends are character ids.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Relationship {
+    pub from: String,
+    pub to: String,
+    pub kind: RelationKind,
+}
+
+/// Rules about the world a timeline has to respect beyond its own events,
+/// read from the `GEN_EVENTS` request. Any field left out keeps its default.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TimelineRules {
+    /// Place id -> faction holding it before any event
+    pub ownership: HashMap<String, String>,
+    pub relationships: Vec<Relationship>,
+    /// Every faction that starts with a ruler must always have someone alive to rule it
+    pub living_ruler: bool,
 }
 
 pub fn ownership_to_json_map(ownership: Ownership) -> HashMap<String, String> {
     let mut map = HashMap::new();
     for (place, faction) in &ownership {
-        map.insert(place.clone().name, faction.clone());
+        map.insert(place.id.clone(), faction.clone());
     }
     map
 }
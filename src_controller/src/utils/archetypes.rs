@@ -0,0 +1,72 @@
+use crate::types::{Archetype, PlaceInfo};
+
+/// Words in a place's name that decide its archetype outright, checked first
+/// since provider categories are often just "tourist_attraction".
+const NAME_RULES: &[(&str, Archetype)] = &[
+    ("castle", Archetype::Castle),
+    ("palace", Archetype::Castle),
+    ("fort", Archetype::Castle),
+    ("hall", Archetype::Castle),
+    ("cathedral", Archetype::Cathedral),
+    ("church", Archetype::Cathedral),
+    ("abbey", Archetype::Cathedral),
+    ("chapel", Archetype::Cathedral),
+    ("minster", Archetype::Cathedral),
+    ("priory", Archetype::Cathedral),
+    ("market", Archetype::Market),
+    ("square", Archetype::Market),
+    ("arcade", Archetype::Market),
+    ("inn", Archetype::Tavern),
+    ("pub", Archetype::Tavern),
+    ("tavern", Archetype::Tavern),
+    ("brewery", Archetype::Tavern),
+    ("tower", Archetype::Tower),
+    ("museum", Archetype::Tower),
+    ("library", Archetype::Tower),
+    ("observatory", Archetype::Tower),
+];
+
+/// Provider place types, in priority order
+const TYPE_RULES: &[(&str, Archetype)] = &[
+    ("city_hall", Archetype::Castle),
+    ("courthouse", Archetype::Castle),
+    ("embassy", Archetype::Castle),
+    ("church", Archetype::Cathedral),
+    ("place_of_worship", Archetype::Cathedral),
+    ("mosque", Archetype::Cathedral),
+    ("synagogue", Archetype::Cathedral),
+    ("hindu_temple", Archetype::Cathedral),
+    ("cemetery", Archetype::Cathedral),
+    ("bar", Archetype::Tavern),
+    ("night_club", Archetype::Tavern),
+    ("restaurant", Archetype::Tavern),
+    ("cafe", Archetype::Tavern),
+    ("lodging", Archetype::Tavern),
+    ("shopping_mall", Archetype::Market),
+    ("store", Archetype::Market),
+    ("supermarket", Archetype::Market),
+    ("department_store", Archetype::Market),
+    ("museum", Archetype::Tower),
+    ("art_gallery", Archetype::Tower),
+    ("library", Archetype::Tower),
+    ("university", Archetype::Tower),
+];
+
+/// Pick the medieval archetype for a place from its name and provider metadata
+pub fn classify(name: &str, info: &PlaceInfo) -> Archetype {
+    let lower = name.to_lowercase();
+    let words: Vec<&str> = lower
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect();
+
+    if let Some(&(_, archetype)) = NAME_RULES.iter().find(|(word, _)| words.contains(word)) {
+        return archetype;
+    }
+
+    TYPE_RULES
+        .iter()
+        .find(|(ty, _)| info.types.iter().any(|t| t == ty))
+        .map(|&(_, archetype)| archetype)
+        .unwrap_or_default()
+}
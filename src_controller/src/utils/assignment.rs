@@ -0,0 +1,109 @@
+/// Assign every row to a column so the total cost is least (Hungarian method).
+///
+/// `costs` is `rows × columns` with no more rows than columns. Returns the
+/// column picked for each row.
+fn hungarian(costs: &[Vec<f64>]) -> Vec<usize> {
+    let n = costs.len();
+    let m = costs.first().map_or(0, |row| row.len());
+
+    // Potentials and matching, 1-indexed with 0 as a sentinel column
+    let mut u = vec![0.0; n + 1];
+    let mut v = vec![0.0; m + 1];
+    let mut row_of = vec![0; m + 1];
+    let mut way = vec![0; m + 1];
+
+    for i in 1..=n {
+        row_of[0] = i;
+        let mut j0 = 0;
+        let mut min_v = vec![f64::INFINITY; m + 1];
+        let mut used = vec![false; m + 1];
+
+        // Grow an alternating tree until it reaches a free column
+        loop {
+            used[j0] = true;
+            let i0 = row_of[j0];
+            let mut delta = f64::INFINITY;
+            let mut j1 = 0;
+            for j in 1..=m {
+                if used[j] {
+                    continue;
+                }
+                let reduced = costs[i0 - 1][j - 1] - u[i0] - v[j];
+                if reduced < min_v[j] {
+                    min_v[j] = reduced;
+                    way[j] = j0;
+                }
+                if min_v[j] < delta {
+                    delta = min_v[j];
+                    j1 = j;
+                }
+            }
+            for j in 0..=m {
+                if used[j] {
+                    u[row_of[j]] += delta;
+                    v[j] -= delta;
+                } else {
+                    min_v[j] -= delta;
+                }
+            }
+            j0 = j1;
+            if row_of[j0] == 0 {
+                break;
+            }
+        }
+
+        // Flip the augmenting path
+        loop {
+            let j1 = way[j0];
+            row_of[j0] = row_of[j1];
+            j0 = j1;
+            if j0 == 0 {
+                break;
+            }
+        }
+    }
+
+    let mut column = vec![0; n];
+    for j in 1..=m {
+        if row_of[j] != 0 {
+            column[row_of[j] - 1] = j - 1;
+        }
+    }
+    column
+}
+
+/// Give each item a group so the total cost is least while no group takes
+/// more than `capacity` items.
+///
+/// `costs[item][group]` is the cost of putting the item in the group. Each
+/// group is split into `capacity` slots and items are matched to slots, so
+/// `capacity * groups` must be at least the number of items.
+pub fn balanced_assignment(costs: &[Vec<f64>], capacity: usize) -> Vec<usize> {
+    let groups = costs.first().map_or(0, |row| row.len());
+    let slots: Vec<Vec<f64>> = costs
+        .iter()
+        .map(|row| (0..groups * capacity).map(|slot| row[slot / capacity]).collect())
+        .collect();
+    hungarian(&slots).into_iter().map(|slot| slot / capacity).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hungarian_finds_optimum() {
+        let costs = vec![vec![4.0, 1.0, 3.0], vec![2.0, 0.0, 5.0], vec![3.0, 2.0, 2.0]];
+        let picks = hungarian(&costs);
+        let total: f64 = picks.iter().enumerate().map(|(i, &j)| costs[i][j]).sum();
+        assert_eq!(total, 5.0);
+    }
+
+    #[test]
+    fn test_capacity_respected_where_greedy_fails() {
+        // Taken in order, the first item would grab group 0 and push the second,
+        // which is far from group 1, out; the optimum swaps them
+        let costs = vec![vec![1.0, 2.0], vec![1.0, 9.0]];
+        assert_eq!(balanced_assignment(&costs, 1), vec![1, 0]);
+    }
+}
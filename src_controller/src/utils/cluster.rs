@@ -1,7 +1,51 @@
 use std::cmp::PartialEq;
-use crate::types::{Event, Map, Ownership, Place};
+use crate::types::{Assignment, ClusterParams, Event, Faction, Map, Metric, Ownership, Place, Region};
+use crate::utils::assignment::balanced_assignment;
+use crate::utils::roads::road_distances;
+use crate::utils::factions::factions;
+use std::cmp::Reverse;
 use std::collections::HashMap;
 use rand::prelude::IndexedRandom;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::Serialize;
+
+/// How clustering went, returned alongside the ownership
+#[derive(Clone, Debug, Serialize)]
+pub struct ClusterMetrics {
+    /// Assign-and-update rounds run
+    pub iterations: usize,
+    /// False if the iteration cap was hit before assignments settled
+    pub converged: bool,
+    /// Sum of squared distances from each place to its cluster's centroid.
+    /// Distances here are along the roads when clustering by road.
+    pub inertia: f64,
+    /// Places per faction, in faction order
+    pub cluster_sizes: Vec<usize>,
+    /// Mean distance from each faction's places to its centroid
+    pub mean_distance: Vec<f64>,
+    /// Distance to each faction's furthest place from its centroid
+    pub max_distance: Vec<f64>,
+    /// Smallest faction's size over the largest's; 1.0 is perfectly even
+    pub size_balance: f64,
+    /// Shortest `max_distance` over the longest among factions holding more
+    /// than one place; low when one faction is far-flung
+    pub spread_balance: f64,
+}
+
+impl ClusterMetrics {
+    /// True when neither fairness ratio falls below `min_balance`
+    pub fn is_balanced(&self, min_balance: f64) -> bool {
+        self.size_balance >= min_balance && self.spread_balance >= min_balance
+    }
+}
+
+/// Smallest value over the largest, or 1.0 when they are all zero
+fn balance(values: &[f64]) -> f64 {
+    let max = values.iter().cloned().fold(0.0, f64::max);
+    let min = values.iter().cloned().fold(f64::INFINITY, f64::min);
+    if max > 0.0 { min / max } else { 1.0 }
+}
 
 /// Compute squared Euclidean distance between two points
 fn dist2(a: (f64, f64), b: (f64, f64)) -> f64 {
@@ -9,19 +53,115 @@ fn dist2(a: (f64, f64), b: (f64, f64)) -> f64 {
     let dy = a.1 - b.1;
     dx * dx + dy * dy
 }
-pub fn cluster_locations(map: &Map) -> Ownership {
-    let factions = ["g", "t", "c"];
-    let k = factions.len();
-    let max_per_cluster = (map.locations.len() as f64 / k as f64).ceil() as usize;
 
-    // Step 1: Initialize centroids randomly
-    let mut centroids: Vec<(f64, f64)> = map.locations
+/// k-means++ seeding: the first centroid is a random place, each further one a
+/// place picked with probability proportional to its squared distance from
+/// the nearest centroid so far
+fn seed_centroids(points: &[(f64, f64)], k: usize, rng: &mut StdRng) -> Vec<(f64, f64)> {
+    let mut centroids = vec![points[rng.random_range(0..points.len())]];
+    while centroids.len() < k {
+        let weights: Vec<f64> = points
+            .iter()
+            .map(|&p| centroids.iter().map(|&c| dist2(p, c)).fold(f64::INFINITY, f64::min))
+            .collect();
+        let total: f64 = weights.iter().sum();
+        // Every place already sits on a centroid; any will do
+        if total <= 0.0 {
+            centroids.push(points[rng.random_range(0..points.len())]);
+            continue;
+        }
+        let mut target = rng.random_range(0.0..total);
+        let mut chosen = points.len() - 1;
+        for (i, &w) in weights.iter().enumerate() {
+            if target < w {
+                chosen = i;
+                break;
+            }
+            target -= w;
+        }
+        centroids.push(points[chosen]);
+    }
+    centroids
+}
+
+/// Split the map's places between the first `params.faction_count` factions
+/// of the registry, no faction taking more than its even share (rounded up).
+///
+/// Centroids start from the largest cities when the realm has at least one
+/// per faction, otherwise from k-means++ seeding driven by `seed`, so the same map
+/// and seed always give the same kingdoms.
+pub fn cluster_locations(map: &Map, params: &ClusterParams, seed: u64) -> (Ownership, ClusterMetrics) {
+    // Never more factions than places, so every faction holds something
+    let factions: Vec<&Faction> = factions()
         .iter()
-        .take(k)
-        .map(|p| p.location)
+        .take(params.faction_count.max(1).min(map.locations.len()))
         .collect();
+    let k = factions.len();
+    if k == 0 {
+        let metrics = ClusterMetrics {
+            iterations: 0,
+            converged: true,
+            inertia: 0.0,
+            cluster_sizes: vec![],
+            mean_distance: vec![],
+            max_distance: vec![],
+            size_balance: 1.0,
+            spread_balance: 1.0,
+        };
+        return (Ownership::new(), metrics);
+    }
+    let max_per_cluster = (map.locations.len() as f64 / k as f64).ceil() as usize;
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    // Step 1: Initialize centroids, starting from the largest cities when the
+    // realm has enough of them, otherwise by k-means++
+    let mut regions: Vec<&Region> = map.regions.iter().filter(|r| !r.places.is_empty()).collect();
+    regions.sort_by_key(|r| Reverse(r.places.len()));
+    let mut centroids: Vec<(f64, f64)> = if regions.len() >= k {
+        regions
+            .iter()
+            .take(k)
+            .map(|r| {
+                let sum = r.places.iter().fold((0.0, 0.0), |acc, &i| {
+                    (acc.0 + map.locations[i].location.0, acc.1 + map.locations[i].location.1)
+                });
+                (sum.0 / r.places.len() as f64, sum.1 / r.places.len() as f64)
+            })
+            .collect()
+    } else {
+        let points: Vec<(f64, f64)> = map.locations.iter().map(|p| p.location).collect();
+        seed_centroids(&points, k, &mut rng)
+    };
+
+    // Road mode measures along the roads between places, so each cluster is
+    // centred on one of its places (a medoid) rather than an average position
+    let road = match params.metric {
+        Metric::Road => Some(road_distances(&map.locations, &map.roads)),
+        Metric::Euclidean => None,
+    };
+    let mut medoids: Vec<usize> = Vec::new();
+    if road.is_some() {
+        for &c in &centroids {
+            let nearest = (0..map.locations.len())
+                .filter(|i| !medoids.contains(i))
+                .min_by(|&a, &b| dist2(map.locations[a].location, c).total_cmp(&dist2(map.locations[b].location, c)))
+                .unwrap();
+            medoids.push(nearest);
+        }
+        centroids = medoids.iter().map(|&m| map.locations[m].location).collect();
+    }
+
+    // Distance from place `i` to cluster `c`, straight to the centroid unless
+    // the roads join the place to the cluster's medoid
+    let distance = |i: usize, c: usize, centroids: &[(f64, f64)], medoids: &[usize]| {
+        if let (Some(road), Some(&m)) = (&road, medoids.get(c)) && road[i][m].is_finite() {
+            return road[i][m];
+        }
+        dist2(map.locations[i].location, centroids[c]).sqrt()
+    };
 
-    let mut ownership: HashMap<String, String> = HashMap::new();
+    // Place name -> cluster
+    let mut ownership: HashMap<String, usize> = HashMap::new();
     let mut changed = true;
     let mut iterations = 0;
     let max_iterations = 100;
@@ -30,57 +170,280 @@ pub fn cluster_locations(map: &Map) -> Ownership {
         iterations += 1;
         changed = false;
 
-        // Step 2: Assign points to closest centroid respecting max_per_cluster
-        let mut clusters: Vec<Vec<&str>> = vec![Vec::new(); k]; // store place names
-        let mut cluster_counts = vec![0; k];
-
-        for place in &map.locations {
-            // compute distances to centroids
-            let mut distances: Vec<(usize, f64)> = centroids.iter()
-                .enumerate()
-                .map(|(i, &(cx, cy))| (i, ((place.location.0 - cx).powi(2) + (place.location.1 - cy).powi(2)).sqrt()))
-                .collect();
-
-            distances.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
-
-            // assign to the closest centroid with available capacity
-            for &(idx, _) in &distances {
-                if cluster_counts[idx] < max_per_cluster {
-                    clusters[idx].push(&place.name);
-                    cluster_counts[idx] += 1;
-                    break;
+        // Step 2: Assign points to centroids respecting max_per_cluster
+        let mut clusters: Vec<Vec<usize>> = vec![Vec::new(); k]; // store place indices
+        let costs: Vec<Vec<f64>> = (0..map.locations.len())
+            .map(|i| (0..k).map(|c| distance(i, c, &centroids, &medoids)).collect())
+            .collect();
+
+        match params.assignment {
+            Assignment::Greedy => {
+                let mut cluster_counts = vec![0; k];
+                for (place, row) in costs.iter().enumerate() {
+                    // compute distances to centroids
+                    let mut distances: Vec<(usize, f64)> = row.iter().cloned().enumerate().collect();
+
+                    distances.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+
+                    // assign to the closest centroid with available capacity
+                    for &(idx, _) in &distances {
+                        if cluster_counts[idx] < max_per_cluster {
+                            clusters[idx].push(place);
+                            cluster_counts[idx] += 1;
+                            break;
+                        }
+                    }
+                }
+            }
+            Assignment::Optimal => {
+                // Least total distance over every place at once, so no place is
+                // pushed far away just because it came late in the list
+                let picks = balanced_assignment(&costs, max_per_cluster);
+                for (place, &idx) in picks.iter().enumerate() {
+                    clusters[idx].push(place);
                 }
             }
         }
 
-        // Step 3: Recompute centroids
+        // Step 3: Recompute centroids; in road mode, move each medoid to the
+        // member with the least total distance to the rest
         for (i, cluster) in clusters.iter().enumerate() {
             if cluster.is_empty() { continue; }
-            let sum = cluster.iter().fold((0.0, 0.0), |acc, name| {
-                let p = map.locations.iter().find(|pl| &pl.name == *name).unwrap();
-                (acc.0 + p.location.0, acc.1 + p.location.1)
-            });
-            centroids[i] = (sum.0 / cluster.len() as f64, sum.1 / cluster.len() as f64);
+            if road.is_some() {
+                let total = |m: usize| cluster.iter().map(|&p| distance(p, 0, &[map.locations[m].location], &[m])).sum::<f64>();
+                medoids[i] = *cluster.iter().min_by(|&&a, &&b| total(a).total_cmp(&total(b))).unwrap();
+                centroids[i] = map.locations[medoids[i]].location;
+            } else {
+                let sum = cluster.iter().fold((0.0, 0.0), |acc, &p| {
+                    (acc.0 + map.locations[p].location.0, acc.1 + map.locations[p].location.1)
+                });
+                centroids[i] = (sum.0 / cluster.len() as f64, sum.1 / cluster.len() as f64);
+            }
         }
 
         // Step 4: Update ownership
         for (i, cluster) in clusters.iter().enumerate() {
-            for &name in cluster {
-                let faction = factions[i];
-                if ownership.get(name) != Some(&faction.to_string()) {
-                    ownership.insert(name.to_string(), faction.to_string());
+            for &p in cluster {
+                let name = &map.locations[p].name;
+                if ownership.get(name) != Some(&i) {
+                    ownership.insert(name.to_string(), i);
                     changed = true;
                 }
             }
         }
     }
 
-    // Step 5: Map ownership back to Place
-    let mut final_ownership = HashMap::new();
+    // Step 5: Hand each cluster to the faction whose home territory suits it best,
+    // scored by how many of its places have an archetype the faction prefers
+    let mut score = vec![vec![0; k]; k]; // [cluster][faction]
     for place in &map.locations {
-        if let Some(faction) = ownership.get(&place.name) {
-            final_ownership.insert(place.clone(), faction.clone());
+        if let Some(&cluster) = ownership.get(&place.name) {
+            for (f, faction) in factions.iter().enumerate() {
+                if faction.territory.archetypes.contains(&place.archetype) {
+                    score[cluster][f] += 1;
+                }
+            }
+        }
+    }
+    let mut holder: Vec<usize> = vec![0; k];
+    let mut cluster_done = vec![false; k];
+    let mut faction_done = vec![false; k];
+    for _ in 0..k {
+        // Best remaining pair; ties go to the lowest cluster and faction
+        let (cluster, faction) = (0..k)
+            .filter(|&c| !cluster_done[c])
+            .flat_map(|c| (0..k).filter(|&f| !faction_done[f]).map(move |f| (c, f)))
+            .max_by_key(|&(c, f)| (score[c][f], Reverse(c), Reverse(f)))
+            .unwrap();
+        holder[cluster] = faction;
+        cluster_done[cluster] = true;
+        faction_done[faction] = true;
+    }
+
+    // Step 6: Map ownership back to Place
+    let mut final_ownership = HashMap::new();
+    let mut cluster_sizes = vec![0; k];
+    let mut total_distance = vec![0.0; k];
+    let mut max_distance = vec![0.0; k];
+    let mut inertia = 0.0;
+    for (i, place) in map.locations.iter().enumerate() {
+        if let Some(&cluster) = ownership.get(&place.name) {
+            let faction = holder[cluster];
+            final_ownership.insert(place.clone(), factions[faction].id.clone());
+
+            let d = distance(i, cluster, &centroids, &medoids);
+            cluster_sizes[faction] += 1;
+            total_distance[faction] += d;
+            max_distance[faction] = f64::max(max_distance[faction], d);
+            inertia += d * d;
+        }
+    }
+
+    // Step 7: Fairness, so lopsided starts can be turned away
+    let mean_distance: Vec<f64> = total_distance
+        .iter()
+        .zip(&cluster_sizes)
+        .map(|(&total, &size)| if size > 0 { total / size as f64 } else { 0.0 })
+        .collect();
+    // A lone place sits on its own centroid, so it says nothing about spread
+    let spreads: Vec<f64> = max_distance
+        .iter()
+        .zip(&cluster_sizes)
+        .filter(|&(_, &size)| size > 1)
+        .map(|(&d, _)| d)
+        .collect();
+    let metrics = ClusterMetrics {
+        iterations,
+        converged: !changed,
+        inertia,
+        size_balance: balance(&cluster_sizes.iter().map(|&s| s as f64).collect::<Vec<_>>()),
+        spread_balance: balance(&spreads),
+        cluster_sizes,
+        mean_distance,
+        max_distance,
+    };
+    (final_ownership, metrics)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Archetype, PlaceInfo, RoadEdge, RoadNetwork};
+
+    fn by_name(ownership: &Ownership) -> Vec<(String, String)> {
+        let mut owned: Vec<(String, String)> = ownership.iter().map(|(p, f)| (p.name.clone(), f.clone())).collect();
+        owned.sort();
+        owned
+    }
+
+    #[test]
+    fn test_same_seed_same_kingdoms() {
+        let m = Map {
+            locations: vec![
+                Place { id: "p0".to_string(), name: "P0".to_string(), location: (-0.8, 0.0), archetype: Archetype::Village, info: PlaceInfo::default() },
+                Place { id: "p1".to_string(), name: "P1".to_string(), location: (-0.7, 0.1), archetype: Archetype::Village, info: PlaceInfo::default() },
+                Place { id: "p2".to_string(), name: "P2".to_string(), location: (0.0, 0.8), archetype: Archetype::Village, info: PlaceInfo::default() },
+                Place { id: "p3".to_string(), name: "P3".to_string(), location: (0.1, 0.7), archetype: Archetype::Village, info: PlaceInfo::default() },
+                Place { id: "p4".to_string(), name: "P4".to_string(), location: (0.8, 0.0), archetype: Archetype::Village, info: PlaceInfo::default() },
+                Place { id: "p5".to_string(), name: "P5".to_string(), location: (0.7, -0.1), archetype: Archetype::Village, info: PlaceInfo::default() },
+            ],
+            roads: RoadNetwork { edges: vec![] },
+            projection: None,
+            features: vec![],
+            regions: vec![],
+        };
+        let params = ClusterParams { faction_count: 3, ..ClusterParams::default() };
+        let (a, metrics) = cluster_locations(&m, &params, 7);
+        let (b, _) = cluster_locations(&m, &params, 7);
+        assert_eq!(by_name(&a), by_name(&b));
+        assert!(metrics.converged);
+        assert_eq!(metrics.cluster_sizes, vec![2, 2, 2]);
+    }
+
+    #[test]
+    fn test_faction_count_is_configurable() {
+        let m = Map {
+            locations: vec![
+                Place { id: "p0".to_string(), name: "P0".to_string(), location: (-0.5, 0.0), archetype: Archetype::Village, info: PlaceInfo::default() },
+                Place { id: "p1".to_string(), name: "P1".to_string(), location: (-0.4, 0.0), archetype: Archetype::Village, info: PlaceInfo::default() },
+                Place { id: "p2".to_string(), name: "P2".to_string(), location: (0.4, 0.0), archetype: Archetype::Village, info: PlaceInfo::default() },
+                Place { id: "p3".to_string(), name: "P3".to_string(), location: (0.5, 0.0), archetype: Archetype::Village, info: PlaceInfo::default() },
+            ],
+            roads: RoadNetwork { edges: vec![] },
+            projection: None,
+            features: vec![],
+            regions: vec![],
+        };
+        let params = ClusterParams { faction_count: 2, ..ClusterParams::default() };
+        let (ownership, metrics) = cluster_locations(&m, &params, 1);
+        let held: std::collections::HashSet<&String> = ownership.values().collect();
+        assert_eq!(held.len(), 2);
+        assert_eq!(metrics.cluster_sizes.len(), 2);
+        // Each pair of neighbours ends up under one banner
+        assert_eq!(ownership[&m.locations[0]], ownership[&m.locations[1]]);
+        assert_eq!(ownership[&m.locations[2]], ownership[&m.locations[3]]);
+    }
+
+    #[test]
+    fn test_optimal_assignment_keeps_factions_compact() {
+        let m = Map {
+            locations: vec![
+                Place { id: "p0".to_string(), name: "P0".to_string(), location: (-0.6, 0.0), archetype: Archetype::Village, info: PlaceInfo::default() },
+                Place { id: "p1".to_string(), name: "P1".to_string(), location: (0.2, 0.0), archetype: Archetype::Village, info: PlaceInfo::default() },
+                Place { id: "p2".to_string(), name: "P2".to_string(), location: (-0.5, 0.0), archetype: Archetype::Village, info: PlaceInfo::default() },
+                Place { id: "p3".to_string(), name: "P3".to_string(), location: (0.9, 0.0), archetype: Archetype::Village, info: PlaceInfo::default() },
+            ],
+            roads: RoadNetwork { edges: vec![] },
+            projection: None,
+            features: vec![],
+            regions: vec![],
+        };
+        let params = ClusterParams { faction_count: 2, assignment: Assignment::Optimal, ..ClusterParams::default() };
+        let (ownership, metrics) = cluster_locations(&m, &params, 3);
+
+        assert_eq!(metrics.cluster_sizes, vec![2, 2]);
+        assert_eq!(ownership[&m.locations[0]], ownership[&m.locations[2]]);
+        assert_eq!(ownership[&m.locations[1]], ownership[&m.locations[3]]);
+        // Even in size, but one faction reaches seven times as far as the other
+        assert_eq!(metrics.size_balance, 1.0);
+        assert!((metrics.spread_balance - 0.05 / 0.35).abs() < 1e-9);
+        assert!(!metrics.is_balanced(0.5));
+    }
+
+    #[test]
+    fn test_lone_places_do_not_skew_spread() {
+        let m = Map {
+            locations: vec![
+                Place { id: "p0".to_string(), name: "P0".to_string(), location: (-0.6, 0.0), archetype: Archetype::Village, info: PlaceInfo::default() },
+                Place { id: "p1".to_string(), name: "P1".to_string(), location: (-0.5, 0.0), archetype: Archetype::Village, info: PlaceInfo::default() },
+                Place { id: "p2".to_string(), name: "P2".to_string(), location: (0.5, 0.0), archetype: Archetype::Village, info: PlaceInfo::default() },
+                Place { id: "p3".to_string(), name: "P3".to_string(), location: (0.6, 0.0), archetype: Archetype::Village, info: PlaceInfo::default() },
+                Place { id: "p4".to_string(), name: "P4".to_string(), location: (0.0, 0.9), archetype: Archetype::Village, info: PlaceInfo::default() },
+            ],
+            roads: RoadNetwork { edges: vec![] },
+            projection: None,
+            features: vec![],
+            regions: vec![],
+        };
+        let params = ClusterParams { faction_count: 3, assignment: Assignment::Optimal, ..ClusterParams::default() };
+        let (_, metrics) = cluster_locations(&m, &params, 2);
+
+        let mut sizes = metrics.cluster_sizes.clone();
+        sizes.sort();
+        assert_eq!(sizes, vec![1, 2, 2]);
+        // Only the two pairs are compared, and they reach equally far
+        assert!((metrics.spread_balance - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_road_metric_follows_the_roads() {
+        // Two places either side of a river, each on its own road east; the
+        // only crossing is a long way round at the far end
+        let m = Map {
+            locations: vec![
+                Place { id: "p0".to_string(), name: "P0".to_string(), location: (-0.5, 0.05), archetype: Archetype::Village, info: PlaceInfo::default() },
+                Place { id: "p1".to_string(), name: "P1".to_string(), location: (-0.5, -0.05), archetype: Archetype::Village, info: PlaceInfo::default() },
+                Place { id: "p2".to_string(), name: "P2".to_string(), location: (0.5, 0.05), archetype: Archetype::Village, info: PlaceInfo::default() },
+                Place { id: "p3".to_string(), name: "P3".to_string(), location: (0.5, -0.05), archetype: Archetype::Village, info: PlaceInfo::default() },
+            ],
+            roads: RoadNetwork {
+                edges: vec![
+                    RoadEdge { from: 0, to: 2, length_m: 0.0, travel_time_s: 0.0, highway: false, path: vec![(-0.5, 0.05), (0.5, 0.05)] },
+                    RoadEdge { from: 1, to: 3, length_m: 0.0, travel_time_s: 0.0, highway: false, path: vec![(-0.5, -0.05), (0.5, -0.05)] },
+                    RoadEdge { from: 2, to: 3, length_m: 0.0, travel_time_s: 0.0, highway: false, path: vec![(0.5, 0.05), (0.95, 0.0), (0.5, -0.05)] },
+                ],
+            },
+            projection: None,
+            features: vec![],
+            regions: vec![],
+        };
+
+        for seed in 0..8 {
+            let params = ClusterParams { faction_count: 2, metric: Metric::Road, ..ClusterParams::default() };
+            let (ownership, _) = cluster_locations(&m, &params, seed);
+            assert_eq!(ownership[&m.locations[0]], ownership[&m.locations[2]]);
+            assert_eq!(ownership[&m.locations[1]], ownership[&m.locations[3]]);
+            assert_ne!(ownership[&m.locations[0]], ownership[&m.locations[1]]);
         }
     }
-    final_ownership
 }
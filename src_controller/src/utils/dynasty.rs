@@ -0,0 +1,263 @@
+use std::collections::{BTreeMap, HashSet};
+use rand::rngs::StdRng;
+use rand::prelude::IndexedRandom;
+use rand::{Rng, SeedableRng};
+
+use crate::types::{Character, Effect, Event, RelationKind, Relationship, Role};
+
+/// Order members inherit in once the ruler's heirs, children and consort are
+/// gone; `None` for those who cannot inherit at all
+fn rank(role: Option<Role>) -> Option<u8> {
+    match role {
+        Some(Role::Ruler) => Some(0),
+        Some(Role::Heir) => Some(1),
+        Some(Role::Knight) => Some(2),
+        Some(Role::Merchant) => Some(3),
+        None => Some(4),
+        // The clergy cannot take the throne
+        Some(Role::Priest) => None,
+    }
+}
+
+fn relate(from: &Character, to: &Character, kind: RelationKind) -> Relationship {
+    Relationship { from: from.id.clone(), to: to.id.clone(), kind }
+}
+
+/// Build the family and fealty ties of a freshly generated roster.
+///
+/// Each faction's heirs are the ruler's children and named to inherit, the
+/// ruler may have a consort, knights and priests are sworn to the ruler, and
+/// each ruler is a rival of the next faction's.
+pub fn gen_relationships(characters: &[Character], seed: u64) -> Vec<Relationship> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut relationships = Vec::new();
+
+    let mut factions: Vec<&str> = characters.iter().map(|c| c.faction.as_str()).collect();
+    factions.sort();
+    factions.dedup();
+
+    let mut rulers: Vec<&Character> = Vec::new();
+    for faction in factions {
+        let members: Vec<&Character> = characters.iter().filter(|c| c.faction == faction).collect();
+        let Some(&ruler) = members.iter().find(|c| c.role == Some(Role::Ruler)) else {
+            continue;
+        };
+        rulers.push(ruler);
+
+        // Step 1: Perhaps a consort, from those who are neither family nor clergy
+        let eligible: Vec<&Character> = members
+            .iter()
+            .filter(|c| matches!(c.role, Some(Role::Knight) | Some(Role::Merchant)))
+            .copied()
+            .collect();
+        let spouse = if rng.random_bool(0.5) { eligible.choose(&mut rng).copied() } else { None };
+        if let Some(spouse) = spouse {
+            relationships.push(relate(ruler, spouse, RelationKind::Spouse));
+            relationships.push(relate(spouse, ruler, RelationKind::Spouse));
+        }
+
+        // Step 2: Heirs are the ruler's children, named to inherit in roster order
+        for &heir in members.iter().filter(|c| c.role == Some(Role::Heir)) {
+            relationships.push(relate(ruler, heir, RelationKind::Parent));
+            if let Some(spouse) = spouse {
+                relationships.push(relate(spouse, heir, RelationKind::Parent));
+            }
+            relationships.push(relate(heir, ruler, RelationKind::Heir));
+        }
+
+        // Step 3: Knights and priests owe the ruler fealty
+        for &vassal in members.iter().filter(|c| matches!(c.role, Some(Role::Knight) | Some(Role::Priest))) {
+            relationships.push(relate(vassal, ruler, RelationKind::SwornTo));
+        }
+    }
+
+    // Step 4: Rulers eye their neighbours' thrones, each pair once
+    for i in 0..rulers.len() {
+        let j = (i + 1) % rulers.len();
+        if i == j || (rulers.len() == 2 && i == 1) {
+            continue;
+        }
+        relationships.push(relate(rulers[i], rulers[j], RelationKind::Rival));
+        relationships.push(relate(rulers[j], rulers[i], RelationKind::Rival));
+    }
+
+    relationships
+}
+
+/// Ids of the ruler of `faction` followed by everyone who would succeed them,
+/// in order. Empty if the faction has no ruler.
+///
+/// Named heirs come first, each followed by their own heirs, then the ruler's
+/// other children, then their consort, then the rest of the faction by rank.
+/// Priests never inherit.
+pub fn line_of_succession(faction: &str, characters: &[Character], relationships: &[Relationship]) -> Vec<String> {
+    let members: Vec<&Character> = characters.iter().filter(|c| c.faction == faction).collect();
+    let Some(ruler) = members.iter().find(|c| c.role == Some(Role::Ruler)) else {
+        return vec![];
+    };
+    let can_inherit = |id: &str| members.iter().any(|c| c.id == id && rank(c.role).is_some());
+    let related = |kind: RelationKind, to: &str| -> Vec<String> {
+        relationships.iter().filter(|r| r.kind == kind && r.to == to).map(|r| r.from.clone()).collect()
+    };
+
+    let mut line = vec![ruler.id.clone()];
+
+    // Step 1: Named heirs, depth first
+    let mut stack: Vec<String> = related(RelationKind::Heir, &ruler.id).into_iter().rev().collect();
+    while let Some(id) = stack.pop() {
+        if line.contains(&id) || !can_inherit(&id) {
+            continue;
+        }
+        stack.extend(related(RelationKind::Heir, &id).into_iter().rev());
+        line.push(id);
+    }
+
+    // Step 2: Other children, then the consort
+    let children = relationships
+        .iter()
+        .filter(|r| r.kind == RelationKind::Parent && r.from == ruler.id)
+        .map(|r| r.to.clone());
+    for id in children.chain(related(RelationKind::Spouse, &ruler.id)) {
+        if !line.contains(&id) && can_inherit(&id) {
+            line.push(id);
+        }
+    }
+
+    // Step 3: Everyone else who can inherit, by rank and then roster order
+    let mut rest: Vec<&&Character> = members.iter().filter(|c| !line.contains(&c.id) && rank(c.role).is_some()).collect();
+    rest.sort_by_key(|c| rank(c.role));
+    line.extend(rest.into_iter().map(|c| c.id.clone()));
+
+    line
+}
+
+/// Ids of everyone dead once `events` have played out in order of `start`.
+/// Deaths recorded on characters count, and a resurrection undoes a death.
+pub fn the_dead(characters: &[Character], events: &[Event]) -> HashSet<String> {
+    // Deaths recorded against events that aren't here happened before them all
+    let mut dead: HashSet<String> = characters
+        .iter()
+        .filter(|c| c.died.as_ref().is_some_and(|d| !events.iter().any(|e| &e.id == d)))
+        .map(|c| c.id.clone())
+        .collect();
+
+    let mut ordered: Vec<&Event> = events.iter().collect();
+    ordered.sort_by(|a, b| a.start.total_cmp(&b.start));
+    for e in ordered {
+        for c in characters.iter().filter(|c| c.died.as_ref() == Some(&e.id)) {
+            dead.insert(c.id.clone());
+        }
+        for effect in &e.effects {
+            match effect {
+                Effect::Death(id) => {
+                    dead.insert(id.clone());
+                }
+                Effect::Resurrection(id) => {
+                    dead.remove(id);
+                }
+                _ => {}
+            }
+        }
+    }
+    dead
+}
+
+/// Who rules each faction once `events` have played out, or `None` when
+/// nobody is left to take the throne. The last to be crowned rules while they
+/// live; otherwise the throne passes down the line of succession.
+pub fn current_rulers(
+    characters: &[Character],
+    relationships: &[Relationship],
+    events: &[Event],
+) -> BTreeMap<String, Option<String>> {
+    let dead = the_dead(characters, events);
+    let mut ordered: Vec<&Event> = events.iter().collect();
+    ordered.sort_by(|a, b| a.start.total_cmp(&b.start));
+    let crowned: Vec<&Character> = ordered
+        .iter()
+        .flat_map(|e| &e.effects)
+        .filter_map(|effect| match effect {
+            Effect::Coronation(id) => characters.iter().find(|c| &c.id == id),
+            _ => None,
+        })
+        .collect();
+
+    let mut rulers = BTreeMap::new();
+    for c in characters.iter().filter(|c| c.role == Some(Role::Ruler)) {
+        let latest = crowned.iter().rev().find(|k| k.faction == c.faction).map(|k| k.id.clone());
+        let line = line_of_succession(&c.faction, characters, relationships);
+        let next = latest.into_iter().chain(line).find(|id| !dead.contains(id.as_str()));
+        rulers.insert(c.faction.clone(), next);
+    }
+    rulers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relationships_for_roster() {
+        let court = vec![
+            Character { id: "king".to_string(), name: "king".to_string(), faction: "g".to_string(), role: Some(Role::Ruler), ..Default::default() },
+            Character { id: "priest".to_string(), name: "priest".to_string(), faction: "g".to_string(), role: Some(Role::Priest), ..Default::default() },
+            Character { id: "knight".to_string(), name: "knight".to_string(), faction: "g".to_string(), role: Some(Role::Knight), ..Default::default() },
+            Character { id: "prince".to_string(), name: "prince".to_string(), faction: "g".to_string(), role: Some(Role::Heir), ..Default::default() },
+            Character { id: "warlord".to_string(), name: "warlord".to_string(), faction: "t".to_string(), role: Some(Role::Ruler), ..Default::default() },
+        ];
+        let relationships = gen_relationships(&court, 5);
+        let has = |from: &str, to: &str, kind| relationships.contains(&Relationship { from: from.into(), to: to.into(), kind });
+
+        assert!(has("king", "prince", RelationKind::Parent));
+        assert!(has("prince", "king", RelationKind::Heir));
+        assert!(has("priest", "king", RelationKind::SwornTo));
+        assert!(has("king", "warlord", RelationKind::Rival) && has("warlord", "king", RelationKind::Rival));
+        assert_eq!(relationships.iter().filter(|r| r.kind == RelationKind::Rival).count(), 2);
+        assert_eq!(relationships, gen_relationships(&court, 5));
+    }
+
+    #[test]
+    fn test_succession_order() {
+        let court = vec![
+            Character { id: "king".to_string(), name: "king".to_string(), faction: "g".to_string(), role: Some(Role::Ruler), ..Default::default() },
+            Character { id: "priest".to_string(), name: "priest".to_string(), faction: "g".to_string(), role: Some(Role::Priest), ..Default::default() },
+            Character { id: "knight".to_string(), name: "knight".to_string(), faction: "g".to_string(), role: Some(Role::Knight), ..Default::default() },
+            Character { id: "prince".to_string(), name: "prince".to_string(), faction: "g".to_string(), role: Some(Role::Heir), ..Default::default() },
+            Character { id: "warlord".to_string(), name: "warlord".to_string(), faction: "t".to_string(), role: Some(Role::Ruler), ..Default::default() },
+        ];
+        let relationships = vec![Relationship { from: "prince".into(), to: "king".into(), kind: RelationKind::Heir }];
+        let line = line_of_succession("g", &court, &relationships);
+        assert_eq!(line, vec!["king", "prince", "knight"]);
+
+        let mut events = vec![Event {
+            id: "e1".to_string(),
+            name: "Regicide".to_string(),
+            description: String::new(),
+            before: vec![],
+            start: 0.0,
+            end: 0.0,
+            _type: "catastrophe".to_string(),
+            characters: vec![],
+            effects: vec![Effect::Death("king".to_string())],
+            requires: vec![],
+            place: None,
+            track: 0.0,
+        }];
+        let rulers = current_rulers(&court, &relationships, &events);
+        assert_eq!(rulers["g"], Some("prince".to_string()));
+        assert_eq!(rulers["t"], Some("warlord".to_string()));
+
+        events[0].effects.push(Effect::Death("warlord".to_string()));
+        assert_eq!(current_rulers(&court, &relationships, &events)["t"], None);
+
+        // Later the warlord rises, and the knight is crowned over the prince
+        let mut later = events[0].clone();
+        later.id = "e2".to_string();
+        later.start = 1.0;
+        later.effects = vec![Effect::Resurrection("warlord".to_string()), Effect::Coronation("knight".to_string())];
+        events.push(later);
+        let rulers = current_rulers(&court, &relationships, &events);
+        assert_eq!(rulers["t"], Some("warlord".to_string()));
+        assert_eq!(rulers["g"], Some("knight".to_string()));
+    }
+}
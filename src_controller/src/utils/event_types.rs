@@ -0,0 +1,124 @@
+use std::collections::HashSet;
+use std::fs;
+use std::sync::OnceLock;
+use rand::Rng;
+use rand::prelude::IndexedRandom;
+
+use crate::types::EventType;
+
+/// Event types file read from the working directory, like `factions.json`
+const EVENT_TYPES_PATH: &str = "event_types.json";
+/// Copy built into the binary, used when the event types file is missing or invalid
+const DEFAULT_EVENT_TYPES: &str = include_str!("../../event_types.json");
+
+/// Every kind of event `gen_event` can create
+#[derive(Clone, Debug)]
+pub struct EventTypeRegistry {
+    types: Vec<EventType>,
+}
+
+impl EventTypeRegistry {
+    /// Parse and check a list of event types, listing every problem found
+    pub fn from_json(data: &str) -> Result<Self, String> {
+        let types: Vec<EventType> = serde_json::from_str(data).map_err(|e| e.to_string())?;
+        if types.is_empty() {
+            return Err("No event types defined".into());
+        }
+
+        let mut problems = Vec::new();
+        let mut ids = HashSet::new();
+        for t in &types {
+            if !ids.insert(t.id.as_str()) {
+                problems.push(format!("event type {} is defined twice", t.id));
+            }
+            if !(t.weight.is_finite() && t.weight > 0.0) {
+                problems.push(format!("{} has weight {}, but weights must be positive", t.id, t.weight));
+            }
+            if !(0.0..=1.0).contains(&t.effect_chance) {
+                problems.push(format!("{} has effect_chance {}, outside 0 to 1", t.id, t.effect_chance));
+            }
+            if t.participants.min > t.participants.max {
+                problems.push(format!("{} wants at least {} participants but at most {}", t.id, t.participants.min, t.participants.max));
+            }
+            for kind in t.required_effects.iter().filter(|k| !t.allowed_effects.contains(k)) {
+                problems.push(format!("{} requires {:?} without allowing it", t.id, kind));
+            }
+        }
+        if !problems.is_empty() {
+            return Err(problems.join("; "));
+        }
+        Ok(EventTypeRegistry { types })
+    }
+
+    /// Load `event_types.json`, falling back to the built-in types when it is
+    /// missing or broken
+    pub fn load() -> Self {
+        match fs::read_to_string(EVENT_TYPES_PATH) {
+            Ok(data) => match Self::from_json(&data) {
+                Ok(registry) => return registry,
+                Err(e) => eprintln!("⚠️ Ignoring {}: {}", EVENT_TYPES_PATH, e),
+            },
+            Err(e) => eprintln!("⚠️ Could not read {}: {}; using built-in event types", EVENT_TYPES_PATH, e),
+        }
+        Self::from_json(DEFAULT_EVENT_TYPES).expect("built-in event types are valid")
+    }
+
+    pub fn get(&self, id: &str) -> Option<&EventType> {
+        self.types.iter().find(|t| t.id == id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &EventType> {
+        self.types.iter()
+    }
+
+    /// Pick a type at random by weight from those `usable` accepts
+    pub fn pick<R: Rng>(&self, rng: &mut R, usable: impl Fn(&EventType) -> bool) -> Option<&EventType> {
+        let candidates: Vec<&EventType> = self.types.iter().filter(|t| usable(t)).collect();
+        candidates.choose_weighted(rng, |t| t.weight).ok().copied()
+    }
+}
+
+/// The registry for this run, loaded on first use
+pub fn event_types() -> &'static EventTypeRegistry {
+    static REGISTRY: OnceLock<EventTypeRegistry> = OnceLock::new();
+    REGISTRY.get_or_init(EventTypeRegistry::load)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use crate::types::EffectKind;
+
+    #[test]
+    fn test_default_event_types_are_valid() {
+        let registry = EventTypeRegistry::from_json(DEFAULT_EVENT_TYPES).unwrap();
+        assert!(registry.get("catastrophe").unwrap().allowed_effects.contains(&EffectKind::Death));
+        assert_eq!(registry.get("conquest").unwrap().required_effects, vec![EffectKind::Transfer]);
+
+        // Catastrophes weigh five of the nine among types that need no effect
+        let mut rng = StdRng::seed_from_u64(3);
+        let picks: Vec<&str> = (0..2000)
+            .map(|_| registry.pick(&mut rng, |t| t.required_effects.is_empty()).unwrap().id.as_str())
+            .collect();
+        let share = picks.iter().filter(|id| **id == "catastrophe").count() as f64 / picks.len() as f64;
+        assert!((share - 5.0 / 9.0).abs() < 0.05, "catastrophe share {}", share);
+        assert!(!picks.contains(&"conquest"));
+    }
+
+    #[test]
+    fn test_problems_reported() {
+        let data = r#"[
+            {"id": "feast", "weight": 0},
+            {"id": "feast", "required_effects": ["death"]},
+            {"id": "duel", "participants": {"min": 3, "max": 2}}
+        ]"#;
+        let err = EventTypeRegistry::from_json(data).unwrap_err();
+        assert!(err.contains("defined twice"));
+        assert!(err.contains("weights must be positive"));
+        assert!(err.contains("without allowing it"));
+        assert!(err.contains("at most 2"));
+        assert!(EventTypeRegistry::from_json("[]").is_err());
+    }
+}
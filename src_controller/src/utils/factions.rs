@@ -0,0 +1,82 @@
+use std::collections::HashSet;
+use std::fs;
+use std::sync::OnceLock;
+
+use crate::types::Faction;
+
+/// Factions file read from the working directory, like `names.json`
+const FACTIONS_PATH: &str = "factions.json";
+/// Copy built into the binary, used when there is no factions file to read
+const DEFAULT_FACTIONS: &str = include_str!("../../factions.json");
+
+/// Every faction in the game, in the order they are handed territory
+#[derive(Clone, Debug)]
+pub struct FactionRegistry {
+    factions: Vec<Faction>,
+}
+
+impl FactionRegistry {
+    /// Parse and check a list of factions
+    pub fn from_json(data: &str) -> Result<Self, String> {
+        let factions: Vec<Faction> = serde_json::from_str(data).map_err(|e| e.to_string())?;
+        if factions.is_empty() {
+            return Err("No factions defined".into());
+        }
+        let mut ids = HashSet::new();
+        for faction in &factions {
+            if !ids.insert(faction.id.as_str()) {
+                return Err(format!("Faction id {} is used twice", faction.id));
+            }
+        }
+        Ok(FactionRegistry { factions })
+    }
+
+    /// Load `factions.json`, falling back to the built-in factions when it is
+    /// missing or broken
+    pub fn load() -> Self {
+        if let Ok(data) = fs::read_to_string(FACTIONS_PATH) {
+            match Self::from_json(&data) {
+                Ok(registry) => return registry,
+                Err(e) => eprintln!("⚠️ Ignoring {}: {}", FACTIONS_PATH, e),
+            }
+        }
+        Self::from_json(DEFAULT_FACTIONS).expect("built-in factions are valid")
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Faction> {
+        self.factions.iter().find(|f| f.id == id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Faction> {
+        self.factions.iter()
+    }
+}
+
+/// The registry for this run, loaded on first use
+pub fn factions() -> &'static FactionRegistry {
+    static REGISTRY: OnceLock<FactionRegistry> = OnceLock::new();
+    REGISTRY.get_or_init(FactionRegistry::load)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_factions_are_valid() {
+        let registry = FactionRegistry::from_json(DEFAULT_FACTIONS).unwrap();
+        assert_eq!(registry.iter().count(), 3);
+        assert_eq!(registry.get("t").unwrap().name, "Trolls");
+        assert!(registry.iter().all(|f| !f.naming.templates.is_empty()));
+    }
+
+    #[test]
+    fn test_duplicate_ids_rejected() {
+        let data = r#"[
+            {"id": "g", "name": "Gnomes", "colour": [0, 255, 0], "emblem": "spr_gnome"},
+            {"id": "g", "name": "Goblins", "colour": [0, 128, 0], "emblem": "spr_goblin"}
+        ]"#;
+        assert!(FactionRegistry::from_json(data).is_err());
+        assert!(FactionRegistry::from_json("[]").is_err());
+    }
+}
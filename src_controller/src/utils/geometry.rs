@@ -0,0 +1,130 @@
+use std::f64::consts::PI;
+
+/// Clip a polygon to the half-plane `normal · p <= offset` (Sutherland–Hodgman)
+pub fn clip_polygon_to_half_plane(polygon: &[(f64, f64)], normal: (f64, f64), offset: f64) -> Vec<(f64, f64)> {
+    let side = |p: (f64, f64)| normal.0 * p.0 + normal.1 * p.1 - offset;
+    let mut out = Vec::new();
+
+    for i in 0..polygon.len() {
+        let a = polygon[i];
+        let b = polygon[(i + 1) % polygon.len()];
+        let (sa, sb) = (side(a), side(b));
+
+        if sa <= 0.0 {
+            out.push(a);
+        }
+        if (sa <= 0.0) != (sb <= 0.0) {
+            let t = sa / (sa - sb);
+            out.push((a.0 + t * (b.0 - a.0), a.1 + t * (b.1 - a.1)));
+        }
+    }
+    out
+}
+
+/// Regular polygon with `segments` sides inscribed in the circle of `radius`
+/// around the origin; its edges are the ones `clip_polygon_to_circle` clips to
+pub fn circle_polygon(radius: f64, segments: usize) -> Vec<(f64, f64)> {
+    (0..segments)
+        .map(|i| {
+            let a = i as f64 * 2.0 * PI / segments as f64;
+            (radius * a.cos(), radius * a.sin())
+        })
+        .collect()
+}
+
+/// Clip a polygon to the circle of `radius` around the origin, approximating
+/// the circle by a regular polygon with `segments` sides
+pub fn clip_polygon_to_circle(polygon: &[(f64, f64)], radius: f64, segments: usize) -> Vec<(f64, f64)> {
+    let edge_offset = radius * (PI / segments as f64).cos();
+    let mut clipped = polygon.to_vec();
+    for i in 0..segments {
+        if clipped.is_empty() {
+            break;
+        }
+        // Normal through the middle of edge i of the circle polygon
+        let a = (i as f64 + 0.5) * 2.0 * PI / segments as f64;
+        clipped = clip_polygon_to_half_plane(&clipped, (a.cos(), a.sin()), edge_offset);
+    }
+    clipped
+}
+
+/// Where the segment `a`–`b` crosses the circle of `radius`, as a fraction along it
+fn circle_crossings(a: (f64, f64), b: (f64, f64), radius: f64) -> Vec<f64> {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let qa = dx * dx + dy * dy;
+    let qb = 2.0 * (a.0 * dx + a.1 * dy);
+    let qc = a.0 * a.0 + a.1 * a.1 - radius * radius;
+    let disc = qb * qb - 4.0 * qa * qc;
+    if qa == 0.0 || disc < 0.0 {
+        return Vec::new();
+    }
+    let root = disc.sqrt();
+    [(-qb - root) / (2.0 * qa), (-qb + root) / (2.0 * qa)]
+        .into_iter()
+        .filter(|t| (0.0..=1.0).contains(t))
+        .collect()
+}
+
+/// Clip a polyline to the circle of `radius` around the origin.
+/// A line that leaves and re-enters the circle comes back as several pieces.
+pub fn clip_polyline_to_circle(points: &[(f64, f64)], radius: f64) -> Vec<Vec<(f64, f64)>> {
+    let inside = |p: (f64, f64)| p.0 * p.0 + p.1 * p.1 <= radius * radius;
+    let lerp = |a: (f64, f64), b: (f64, f64), t: f64| (a.0 + t * (b.0 - a.0), a.1 + t * (b.1 - a.1));
+
+    let mut pieces = Vec::new();
+    let mut current: Vec<(f64, f64)> = Vec::new();
+
+    for (i, &p) in points.iter().enumerate() {
+        if i > 0 {
+            let prev = points[i - 1];
+            for t in circle_crossings(prev, p, radius) {
+                current.push(lerp(prev, p, t));
+                // A crossing with a piece already open means the line is leaving
+                if current.len() > 1 {
+                    pieces.push(std::mem::take(&mut current));
+                }
+            }
+        }
+        if inside(p) {
+            current.push(p);
+        }
+    }
+
+    if current.len() > 1 {
+        pieces.push(current);
+    }
+    pieces.retain(|piece| piece.len() > 1);
+    pieces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_polyline_crossing_circle() {
+        // A straight line through the middle is cut at both edges of the circle
+        let pieces = clip_polyline_to_circle(&[(-2.0, 0.0), (0.0, 0.0), (2.0, 0.0)], 1.0);
+        assert_eq!(pieces.len(), 1);
+        let piece = &pieces[0];
+        assert!((piece[0].0 + 1.0).abs() < 1e-9);
+        assert!((piece.last().unwrap().0 - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_polyline_leaving_and_returning() {
+        let line = vec![(-0.5, 0.0), (0.0, 2.0), (0.5, 0.0)];
+        let pieces = clip_polyline_to_circle(&line, 1.0);
+        assert_eq!(pieces.len(), 2);
+        assert_eq!(pieces[0][0], (-0.5, 0.0));
+        assert_eq!(*pieces[1].last().unwrap(), (0.5, 0.0));
+    }
+
+    #[test]
+    fn test_polygon_clipped_to_circle() {
+        let square = vec![(-2.0, -2.0), (2.0, -2.0), (2.0, 2.0), (-2.0, 2.0)];
+        let clipped = clip_polygon_to_circle(&square, 1.0, 64);
+        assert!(clipped.iter().all(|p| (p.0 * p.0 + p.1 * p.1).sqrt() <= 1.0 + 1e-9));
+        assert!(clipped.len() >= 64);
+    }
+}
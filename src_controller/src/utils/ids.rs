@@ -0,0 +1,191 @@
+use std::collections::{HashMap, HashSet};
+use rand::Rng;
+
+use crate::types::{Character, Event};
+
+/// Stable 64-bit FNV-1a hash, so the same name and seed give the same result across builds
+pub fn fnv1a(s: &str) -> u64 {
+    s.bytes().fold(0xcbf29ce484222325, |h, b| (h ^ b as u64).wrapping_mul(0x100000001b3))
+}
+
+/// Opaque id such as `ev-8c1f…`, the same every time for the same `seed`
+pub fn make_id(prefix: &str, seed: &str) -> String {
+    format!("{}-{:016x}", prefix, fnv1a(seed))
+}
+
+/// Opaque id for something new, with no name to derive it from yet
+pub fn fresh_id<R: Rng>(prefix: &str, rng: &mut R) -> String {
+    format!("{}-{:016x}", prefix, rng.random::<u64>())
+}
+
+/// Id for a place that wasn't given one, e.g. from a map saved before places had ids
+pub fn place_id(name: &str, location: (f64, f64)) -> String {
+    make_id("pl", &format!("{}@{},{}", name, location.0, location.1))
+}
+
+/// Id for a character sent without one, from their name and faction. Only
+/// legacy payloads need this; new characters get a `fresh_id`.
+pub fn character_id(name: &str, faction: &str) -> String {
+    make_id("ch", &format!("{}/{}", faction, name))
+}
+
+/// Give ids to events and characters sent without them, and point every
+/// reference still made by display name at the matching id.
+///
+/// Payloads from before ids existed key `before`, effects, `born` and `died`
+/// on names; those are converted, and anything already keyed on ids is left
+/// alone, so running this twice changes nothing. Place references are left
+/// as they are, since only the map knows place names.
+pub fn upgrade_legacy(events: &mut [Event], characters: &mut [Character]) {
+    // Step 1: Characters take their id from name and faction, so the copies
+    // carried by events match the roster
+    let participants = events.iter_mut().flat_map(|e| e.characters.iter_mut());
+    for c in characters.iter_mut().chain(participants) {
+        if c.id.is_empty() {
+            c.id = character_id(&c.name, &c.faction);
+        }
+    }
+
+    // Step 2: Events take theirs from their name, numbered apart when names repeat
+    let mut taken: HashSet<String> = events.iter().map(|e| e.id.clone()).filter(|id| !id.is_empty()).collect();
+    for e in events.iter_mut().filter(|e| e.id.is_empty()) {
+        let mut id = make_id("ev", &e.name);
+        let mut n = 2;
+        while taken.contains(&id) {
+            id = make_id("ev", &format!("{}#{}", e.name, n));
+            n += 1;
+        }
+        taken.insert(id.clone());
+        e.id = id;
+    }
+
+    // Step 3: Rewrite references. The first of several with the same name wins,
+    // which is all a name-keyed payload could have meant anyway.
+    let mut event_ids: HashMap<String, String> = HashMap::new();
+    for e in events.iter() {
+        event_ids.insert(e.id.clone(), e.id.clone());
+    }
+    for e in events.iter() {
+        event_ids.entry(e.name.clone()).or_insert_with(|| e.id.clone());
+    }
+    let mut character_ids: HashMap<String, String> = HashMap::new();
+    let everyone: Vec<&Character> = characters.iter().chain(events.iter().flat_map(|e| &e.characters)).collect();
+    for c in &everyone {
+        character_ids.insert(c.id.clone(), c.id.clone());
+    }
+    for c in &everyone {
+        character_ids.entry(c.name.clone()).or_insert_with(|| c.id.clone());
+    }
+
+    let resolve = |ids: &HashMap<String, String>, r: &mut String| {
+        if let Some(id) = ids.get(r.as_str()) {
+            *r = id.clone();
+        }
+    };
+    for e in events.iter_mut() {
+        e.before.iter_mut().for_each(|b| resolve(&event_ids, b));
+        for c in e.effects.iter_mut().flat_map(|effect| effect.characters_mut()) {
+            resolve(&character_ids, c);
+        }
+    }
+    let participants = events.iter_mut().flat_map(|e| e.characters.iter_mut());
+    for c in characters.iter_mut().chain(participants) {
+        c.born.iter_mut().for_each(|b| resolve(&event_ids, b));
+        c.died.iter_mut().for_each(|d| resolve(&event_ids, d));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Effect;
+
+    #[test]
+    fn test_legacy_names_converted() {
+        let mut roster = vec![Character { name: "Bob".to_string(), faction: "t".to_string(), ..Default::default() }];
+        let mut events = vec![
+            Event {
+                id: String::new(),
+                name: "Feast".to_string(),
+                description: String::new(),
+                before: vec!["Plague".to_string()],
+                start: 0.0,
+                end: 0.0,
+                _type: "normal".to_string(),
+                characters: vec![Character { name: "Bob".to_string(), faction: "t".to_string(), ..Default::default() }],
+                effects: vec![],
+                requires: vec![],
+                place: None,
+                track: 0.0,
+            },
+            Event {
+                id: String::new(),
+                name: "Plague".to_string(),
+                description: String::new(),
+                before: vec![],
+                start: 0.0,
+                end: 0.0,
+                _type: "normal".to_string(),
+                characters: vec![],
+                effects: vec![Effect::Death("Bob".to_string())],
+                requires: vec![],
+                place: None,
+                track: 0.0,
+            },
+        ];
+        upgrade_legacy(&mut events, &mut roster);
+
+        assert!(events.iter().all(|e| e.id.starts_with("ev-")));
+        assert_eq!(events[0].before, vec![events[1].id.clone()]);
+        assert_eq!(events[0].characters[0].id, roster[0].id);
+        assert!(matches!(&events[1].effects[0], Effect::Death(id) if *id == roster[0].id));
+
+        // Already converted payloads come through untouched
+        let (before, ids): (Vec<Event>, Vec<Character>) = (events.clone(), roster.clone());
+        upgrade_legacy(&mut events, &mut roster);
+        assert_eq!(events[0].before, before[0].before);
+        assert_eq!(roster[0].id, ids[0].id);
+    }
+
+    #[test]
+    fn test_shared_names_get_distinct_ids() {
+        let mut roster = vec![
+            Character { name: "Ash".to_string(), faction: "g".to_string(), ..Default::default() },
+            Character { name: "Ash".to_string(), faction: "t".to_string(), ..Default::default() },
+        ];
+        let mut events = vec![
+            Event {
+                id: String::new(),
+                name: "Raid".to_string(),
+                description: String::new(),
+                before: vec![],
+                start: 0.0,
+                end: 0.0,
+                _type: "normal".to_string(),
+                characters: vec![],
+                effects: vec![],
+                requires: vec![],
+                place: None,
+                track: 0.0,
+            },
+            Event {
+                id: String::new(),
+                name: "Raid".to_string(),
+                description: String::new(),
+                before: vec![],
+                start: 0.0,
+                end: 0.0,
+                _type: "normal".to_string(),
+                characters: vec![],
+                effects: vec![],
+                requires: vec![],
+                place: None,
+                track: 0.0,
+            },
+        ];
+        upgrade_legacy(&mut events, &mut roster);
+
+        assert_ne!(roster[0].id, roster[1].id);
+        assert_ne!(events[0].id, events[1].id);
+    }
+}
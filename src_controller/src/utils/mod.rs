@@ -1,2 +1,15 @@
+pub mod archetypes;
+pub mod assignment;
 pub mod cluster;
-pub mod prompt;
\ No newline at end of file
+pub mod dynasty;
+pub mod event_types;
+pub mod factions;
+pub mod geometry;
+pub mod ids;
+pub mod names;
+pub mod projection;
+pub mod prompt;
+pub mod roads;
+pub mod simplify;
+pub mod territory;
+pub mod validate;
\ No newline at end of file
@@ -0,0 +1,106 @@
+use std::collections::HashMap;
+use std::fs;
+use std::sync::OnceLock;
+
+use crate::types::Character;
+use crate::utils::factions::factions;
+
+/// Names file read from the working directory, like `factions.json`
+const NAMES_PATH: &str = "names.json";
+/// Copy built into the binary, used when the names file is missing or invalid
+const DEFAULT_NAMES: &str = include_str!("../../names.json");
+
+/// Every character name the roster generator learns from, by faction
+#[derive(Clone, Debug)]
+pub struct NamePool {
+    characters: Vec<Character>,
+}
+
+impl NamePool {
+    /// Parse and check a names file, listing every problem found
+    pub fn from_json(data: &str) -> Result<Self, String> {
+        let characters: Vec<Character> = serde_json::from_str(data).map_err(|e| e.to_string())?;
+        if characters.is_empty() {
+            return Err("No names defined".into());
+        }
+
+        let mut problems = Vec::new();
+        let mut seen: HashMap<String, &str> = HashMap::new();
+        for c in &characters {
+            if factions().get(&c.faction).is_none() {
+                problems.push(format!("\"{}\" belongs to unknown faction \"{}\"", c.name, c.faction));
+            }
+            if let Some(faction) = seen.insert(c.name.to_lowercase(), &c.faction) {
+                problems.push(format!("\"{}\" is listed twice (factions {} and {})", c.name, faction, c.faction));
+            }
+        }
+        if !problems.is_empty() {
+            return Err(problems.join("; "));
+        }
+        Ok(NamePool { characters })
+    }
+
+    /// Load `names.json`, falling back to the built-in names when it is
+    /// missing or broken
+    pub fn load() -> Self {
+        match fs::read_to_string(NAMES_PATH) {
+            Ok(data) => match Self::from_json(&data) {
+                Ok(pool) => return pool,
+                Err(e) => eprintln!("⚠️ Ignoring {}: {}", NAMES_PATH, e),
+            },
+            Err(e) => eprintln!("⚠️ Could not read {}: {}; using built-in names", NAMES_PATH, e),
+        }
+        Self::from_json(DEFAULT_NAMES).expect("built-in names are valid")
+    }
+
+    pub fn characters(&self) -> &[Character] {
+        &self.characters
+    }
+
+    /// Check each of the first `faction_count` factions has at least
+    /// `roster_size` names to fall back on
+    pub fn check_roster(&self, faction_count: usize, roster_size: usize) -> Result<(), String> {
+        let problems: Vec<String> = factions()
+            .iter()
+            .take(faction_count)
+            .filter_map(|faction| {
+                let count = self.characters.iter().filter(|c| c.faction == faction.id).count();
+                (count < roster_size).then(|| {
+                    format!("only {} name(s) for the {}, but rosters of {} were asked for", count, faction.name, roster_size)
+                })
+            })
+            .collect();
+        if problems.is_empty() { Ok(()) } else { Err(problems.join("; ")) }
+    }
+}
+
+/// The name pool for this run, loaded and checked on first use
+pub fn names() -> &'static NamePool {
+    static POOL: OnceLock<NamePool> = OnceLock::new();
+    POOL.get_or_init(NamePool::load)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_names_are_valid() {
+        let pool = NamePool::from_json(DEFAULT_NAMES).unwrap();
+        assert!(pool.check_roster(3, 4).is_ok());
+        assert!(pool.check_roster(3, 1000).unwrap_err().contains("Gnomes"));
+    }
+
+    #[test]
+    fn test_problems_reported() {
+        let data = r#"[
+            {"name": "Grak", "faction": "t"},
+            {"name": "grak", "faction": "g"},
+            {"name": "Zed", "faction": "x"}
+        ]"#;
+        let err = NamePool::from_json(data).unwrap_err();
+        assert!(err.contains("listed twice"));
+        assert!(err.contains("unknown faction \"x\""));
+        assert!(NamePool::from_json("[]").is_err());
+    }
+}
@@ -0,0 +1,104 @@
+use serde::{Deserialize, Serialize};
+
+const EARTH_RADIUS_M: f64 = 6371000.0;
+
+/// Local equirectangular projection from (lat, lng) degrees into game space.
+///
+/// Longitude is scaled by the cosine of the origin's latitude so that a metre
+/// east and a metre north cover the same distance on screen. Game space is
+/// oriented with +x east and +y north, so north is always up.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Projection {
+    /// (lat, lng) of the point placed at the centre of the unit circle
+    pub origin: (f64, f64),
+    /// Game units per metre
+    pub scale: f64,
+}
+
+impl Projection {
+    /// Centre the projection on the centroid of `points` and scale it so the
+    /// farthest point lands `radius` away from the centre.
+    pub fn fit(points: &[(f64, f64)], radius: f64) -> Projection {
+        let n = points.len().max(1) as f64;
+        let (sum_lat, sum_lng) = points
+            .iter()
+            .fold((0.0, 0.0), |acc, p| (acc.0 + p.0, acc.1 + p.1));
+
+        let unscaled = Projection { origin: (sum_lat / n, sum_lng / n), scale: 1.0 };
+        let max_dist_m = points
+            .iter()
+            .map(|&p| {
+                let (x, y) = unscaled.forward(p);
+                (x * x + y * y).sqrt()
+            })
+            .fold(0.0, f64::max)
+            .max(1e-9);
+
+        Projection { scale: radius / max_dist_m, ..unscaled }
+    }
+
+    /// (lat, lng) → game (x, y)
+    pub fn forward(&self, (lat, lng): (f64, f64)) -> (f64, f64) {
+        let cos_lat = self.origin.0.to_radians().cos();
+        let east_m = (lng - self.origin.1).to_radians() * cos_lat * EARTH_RADIUS_M;
+        let north_m = (lat - self.origin.0).to_radians() * EARTH_RADIUS_M;
+        (east_m * self.scale, north_m * self.scale)
+    }
+
+    /// Game (x, y) → (lat, lng)
+    pub fn inverse(&self, (x, y): (f64, f64)) -> (f64, f64) {
+        let cos_lat = self.origin.0.to_radians().cos();
+        let lat = self.origin.0 + (y / self.scale / EARTH_RADIUS_M).to_degrees();
+        let lng = self.origin.1 + (x / self.scale / EARTH_RADIUS_M / cos_lat).to_degrees();
+        (lat, lng)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let points = vec![(52.95, -1.15), (52.96, -1.13), (52.94, -1.16)];
+        let projection = Projection::fit(&points, 0.9);
+
+        for &p in &points {
+            let back = projection.inverse(projection.forward(p));
+            assert!((back.0 - p.0).abs() < 1e-9 && (back.1 - p.1).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_fit_uses_radius_and_north_is_up() {
+        let points = vec![(52.95, -1.15), (52.96, -1.13), (52.94, -1.16)];
+        let projection = Projection::fit(&points, 0.9);
+
+        let max = points
+            .iter()
+            .map(|&p| {
+                let (x, y) = projection.forward(p);
+                (x * x + y * y).sqrt()
+            })
+            .fold(0.0, f64::max);
+        assert!((max - 0.9).abs() < 1e-9);
+
+        // The northernmost point has the largest y, the easternmost the largest x
+        assert!(projection.forward((52.96, -1.13)).1 > projection.forward((52.95, -1.15)).1);
+        assert!(projection.forward((52.96, -1.13)).0 > projection.forward((52.94, -1.16)).0);
+    }
+
+    #[test]
+    fn test_no_east_west_stretch() {
+        // At UK latitudes a degree of longitude is much shorter than a degree of latitude,
+        // so equal ground distances east and north must map to equal game distances
+        let projection = Projection { origin: (53.0, -1.0), scale: 1.0 };
+        let one_km_north = projection.forward((53.0 + (1000.0 / EARTH_RADIUS_M).to_degrees(), -1.0));
+        let one_km_east = projection.forward((
+            53.0,
+            -1.0 + (1000.0 / EARTH_RADIUS_M / 53.0f64.to_radians().cos()).to_degrees(),
+        ));
+        assert!((one_km_north.1 - 1000.0).abs() < 1e-6);
+        assert!((one_km_east.0 - 1000.0).abs() < 1e-6);
+    }
+}
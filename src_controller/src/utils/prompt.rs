@@ -1,7 +1,10 @@
-use crate::types::{Effect, Event};
+use crate::types::{Archetype, Event, Faction};
+use crate::utils::event_types::event_types;
+use crate::solver::effects::{Naming, semantics};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use dotenvy::dotenv;
+use std::collections::HashMap;
 use std::env;
 
 #[tokio::main]
@@ -19,13 +22,24 @@ struct EventWithNameDescription {
     description: String,
 }
 
-pub async fn get_name_and_description(mut event: Event) -> Result<Event, Box<dyn std::error::Error>> {
+/// Name and describe `event`. References to other events are given to the LLM
+/// by name, looked up in `events`; place ids by their name in `place_names`.
+pub async fn get_name_and_description(
+    mut event: Event,
+    events: &[Event],
+    place_names: &HashMap<String, String>,
+) -> Result<Event, Box<dyn std::error::Error>> {
     dotenv().ok();
     // Prepare the fields for the prompt
     let before = if event.before.is_empty() {
         "".to_string()
     } else {
-        event.before.join(", ")
+        event
+            .before
+            .iter()
+            .map(|id| events.iter().find(|e| &e.id == id).map(|e| e.name.as_str()).unwrap_or(id))
+            .collect::<Vec<_>>()
+            .join(", ")
     };
 
     let characters = if event.characters.is_empty() {
@@ -34,7 +48,10 @@ pub async fn get_name_and_description(mut event: Event) -> Result<Event, Box<dyn
         event
             .characters
             .iter()
-            .map(|c| c.name.clone()) // assuming Character has a `name` field
+            .map(|c| match c.role {
+                Some(role) => format!("{} ({:?})", c.name, role),
+                None => c.name.clone(),
+            })
             .collect::<Vec<_>>()
             .join(", ")
     };
@@ -48,11 +65,22 @@ pub async fn get_name_and_description(mut event: Event) -> Result<Event, Box<dyn
         event._type, before, characters
     );
 
-    if event.effects.iter().any(|e| matches!(e, Effect::Death(_))) && !event.characters.is_empty() {
-        let character_name = &event.characters[0].name; // Pick the first character affected
-        prompt.push_str(&format!(" The {} should cause {} to die.", event._type, character_name));
+    // What the event changes, as the solver understands it
+    let naming = Naming { characters: &event.characters, places: place_names };
+    for effect in &event.effects {
+        if let Some(outcome) = semantics(effect.kind()).and_then(|s| s.describe(effect, &naming)) {
+            prompt.push_str(&format!(" The {} should {}.", event._type, outcome));
+        }
     }
 
+    if let Some(event_type) = event_types().get(&event._type) {
+        if let Some(archetype) = event_type.archetype {
+            prompt.push_str(&format!(" It would suit a {:?}.", archetype));
+        }
+        if !event_type.flavour.is_empty() {
+            prompt.push_str(&format!(" Draw on themes such as {}.", event_type.flavour.join(", ")));
+        }
+    }
 
     prompt.push_str(" Write the description as a request from a member of the kingdom to the player, in a concise, fun, medieval tone (1–2 sentences). Return the result as JSON in the format {\"name\": ..., \"description\": ... }.");
 
@@ -85,3 +113,60 @@ pub async fn get_name_and_description(mut event: Event) -> Result<Event, Box<dyn
 
     Ok(event)
 }
+
+
+#[derive(Serialize, Deserialize)]
+struct PlaceName {
+    name: String,
+}
+
+/// Polish a rule-based medieval place name with the LLM.
+/// Runs at temperature 0 with a fixed seed so repeated calls stay as stable as the API allows.
+pub async fn get_medieval_place_name(
+    original: &str,
+    draft: &str,
+    archetype: Archetype,
+    faction: Option<&Faction>,
+    seed: u64,
+) -> Result<String, Box<dyn std::error::Error>> {
+    dotenv().ok();
+
+    let mut prompt = format!(
+        "Rename a real place for a medieval-fantasy kingdom. \
+        Its modern name is \"{}\" and it serves as a {:?}. \
+        A first draft of the new name is \"{}\".",
+        original, archetype, draft
+    );
+
+    if let Some(faction) = faction {
+        prompt.push_str(&format!(
+            " It is held by the {} ({}), so flavour the name to suit them.",
+            faction.name, faction.naming.style
+        ));
+    }
+
+    prompt.push_str(" Keep it short (1–4 words) and recognisably inspired by the modern name. Return the result as JSON in the format {\"name\": ... }.");
+
+    let api_key = env::var("OPENAI_API_KEY")?;
+    let client = reqwest::Client::new();
+    let body = json!({
+        "model": "gpt-4",
+        "messages": [{"role": "user", "content": prompt}],
+        "temperature": 0.0,
+        "seed": seed
+    });
+
+    let res = client
+        .post("https://api.openai.com/v1/chat/completions")
+        .bearer_auth(api_key)
+        .json(&body)
+        .send()
+        .await?;
+
+    let res_json: serde_json::Value = res.json().await?;
+    let content = &res_json["choices"][0]["message"]["content"];
+    let content_str = content.as_str().ok_or("Missing response content")?;
+
+    let place_name: PlaceName = serde_json::from_str(content_str)?;
+    Ok(place_name.name)
+}
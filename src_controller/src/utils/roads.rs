@@ -0,0 +1,292 @@
+use petgraph::algo::dijkstra;
+use petgraph::graph::{NodeIndex, UnGraph};
+use crate::types::{Place, RoadEdge, RoadNetwork};
+use crate::utils::projection::Projection;
+
+/// Squared Euclidean distance between two points
+fn dist2(a: (f64, f64), b: (f64, f64)) -> f64 {
+    let dx = a.0 - b.0;
+    let dy = a.1 - b.1;
+    dx * dx + dy * dy
+}
+
+/// Pick which pairs of places should be joined by a road.
+///
+/// Uses the Gabriel graph: `i` and `j` are linked when no other place lies inside
+/// the circle whose diameter is the segment `i`–`j`. It contains the minimum
+/// spanning tree (so every place is reachable), is planar like a Delaunay
+/// triangulation, and skips the long diagonals a real road would never take.
+/// The result does not depend on the order the places were supplied in.
+pub fn plan_roads(points: &[(f64, f64)]) -> Vec<(usize, usize)> {
+    let mut pairs = Vec::new();
+    for i in 0..points.len() {
+        for j in (i + 1)..points.len() {
+            let mid = ((points[i].0 + points[j].0) / 2.0, (points[i].1 + points[j].1) / 2.0);
+            let r2 = dist2(points[i], points[j]) / 4.0;
+
+            let blocked = points
+                .iter()
+                .enumerate()
+                .any(|(k, &p)| k != i && k != j && dist2(p, mid) < r2);
+
+            if !blocked {
+                pairs.push((i, j));
+            }
+        }
+    }
+    pairs
+}
+
+/// Pick the highways joining the regions of a multi-city realm.
+///
+/// Builds a minimum spanning tree over the regions, where the distance between
+/// two regions is that of their closest pair of places, and returns that pair
+/// (as indices into `points`) for each tree edge.
+pub fn plan_highways(points: &[(f64, f64)], regions: &[Vec<usize>]) -> Vec<(usize, usize)> {
+    let closest = |a: &[usize], b: &[usize]| {
+        a.iter()
+            .flat_map(|&i| b.iter().map(move |&j| (i, j)))
+            .min_by(|&(i, j), &(k, l)| dist2(points[i], points[j]).total_cmp(&dist2(points[k], points[l])))
+    };
+
+    // Prim's algorithm over regions
+    let mut in_tree = vec![false; regions.len()];
+    let mut highways = Vec::new();
+    if let Some(first) = regions.iter().position(|r| !r.is_empty()) {
+        in_tree[first] = true;
+    }
+    loop {
+        let next = (0..regions.len())
+            .filter(|&a| in_tree[a])
+            .flat_map(|a| (0..regions.len()).filter(|&b| !in_tree[b]).map(move |b| (a, b)))
+            .filter_map(|(a, b)| closest(&regions[a], &regions[b]).map(|pair| (b, pair)))
+            .min_by(|(_, (i, j)), (_, (k, l))| dist2(points[*i], points[*j]).total_cmp(&dist2(points[*k], points[*l])));
+
+        match next {
+            Some((region, pair)) => {
+                in_tree[region] = true;
+                highways.push(pair);
+            }
+            None => break,
+        }
+    }
+    highways
+}
+
+/// Index of the place closest to `point`
+fn nearest_place(locations: &[Place], point: (f64, f64)) -> Option<usize> {
+    locations
+        .iter()
+        .enumerate()
+        .min_by(|a, b| dist2(a.1.location, point).total_cmp(&dist2(b.1.location, point)))
+        .map(|(i, _)| i)
+}
+
+/// Rebuild a road network from bare polylines (e.g. maps saved before roads
+/// were linked to places) by snapping each route's endpoints to the nearest place.
+/// Lengths and travel times are unknown for these roads and are left at zero.
+pub fn link_routes(locations: &[Place], routes: Vec<Vec<(f64, f64)>>) -> RoadNetwork {
+    let edges = routes
+        .into_iter()
+        .filter_map(|path| {
+            let from = nearest_place(locations, *path.first()?)?;
+            let to = nearest_place(locations, *path.last()?)?;
+            Some(RoadEdge { from, to, length_m: 0.0, travel_time_s: 0.0, highway: false, path })
+        })
+        .collect();
+    RoadNetwork { edges }
+}
+
+/// Assumed speed on roads the provider never routed, in metres per second (~30 km/h)
+const SYNTHETIC_ROAD_SPEED: f64 = 8.3;
+
+/// A straight road between two places, for when no real route is available.
+/// Length and travel time are estimated from the projection when there is one.
+pub fn straight_road(locations: &[Place], from: usize, to: usize, projection: Option<&Projection>) -> RoadEdge {
+    let (a, b) = (locations[from].location, locations[to].location);
+    let length_m = projection.map_or(0.0, |p| dist2(a, b).sqrt() / p.scale);
+    RoadEdge {
+        from,
+        to,
+        length_m,
+        travel_time_s: length_m / SYNTHETIC_ROAD_SPEED,
+        highway: false,
+        path: vec![a, b],
+    }
+}
+
+/// How far a synthesised curved road bows out, as a fraction of its length
+const CURVE_BEND: f64 = 0.15;
+/// Points used to draw a synthesised curved road
+const CURVE_POINTS: usize = 9;
+
+/// A road bowing gently between two places, for streets the provider couldn't
+/// route; looks less surveyed than a straight line. `side` picks which way it
+/// bows (positive is to the left going from `from` to `to`).
+pub fn curved_road(locations: &[Place], from: usize, to: usize, projection: Option<&Projection>, side: f64) -> RoadEdge {
+    let straight = straight_road(locations, from, to, projection);
+    let (a, b) = (locations[from].location, locations[to].location);
+    let chord = dist2(a, b).sqrt();
+    if chord == 0.0 {
+        return straight;
+    }
+
+    // Quadratic Bézier whose middle sits CURVE_BEND * chord off the straight line
+    let bow = 2.0 * CURVE_BEND * side.signum();
+    let control = ((a.0 + b.0) / 2.0 - (b.1 - a.1) * bow, (a.1 + b.1) / 2.0 + (b.0 - a.0) * bow);
+    let path: Vec<(f64, f64)> = (0..CURVE_POINTS)
+        .map(|i| {
+            let t = i as f64 / (CURVE_POINTS - 1) as f64;
+            let (u, v, w) = ((1.0 - t) * (1.0 - t), 2.0 * (1.0 - t) * t, t * t);
+            (u * a.0 + v * control.0 + w * b.0, u * a.1 + v * control.1 + w * b.1)
+        })
+        .collect();
+
+    let stretch = path.windows(2).map(|w| dist2(w[0], w[1]).sqrt()).sum::<f64>() / chord;
+    RoadEdge {
+        length_m: straight.length_m * stretch,
+        travel_time_s: straight.travel_time_s * stretch,
+        path,
+        ..straight
+    }
+}
+
+/// Label every place with the id of the connected component it belongs to
+pub fn components(place_count: usize, roads: &RoadNetwork) -> Vec<usize> {
+    let mut component = vec![usize::MAX; place_count];
+    for start in 0..place_count {
+        if component[start] != usize::MAX {
+            continue;
+        }
+        let mut stack = vec![start];
+        while let Some(i) = stack.pop() {
+            if component[i] != usize::MAX {
+                continue;
+            }
+            component[i] = start;
+            stack.extend(roads.neighbours(i).map(|(j, _)| j).filter(|&j| j < place_count));
+        }
+    }
+    component
+}
+
+/// Shortest distance along the roads between every pair of places, in game
+/// units; `f64::INFINITY` where no roads join them
+pub fn road_distances(locations: &[Place], roads: &RoadNetwork) -> Vec<Vec<f64>> {
+    let mut graph = UnGraph::<(), f64>::new_undirected();
+    let nodes: Vec<NodeIndex> = locations.iter().map(|_| graph.add_node(())).collect();
+    for edge in &roads.edges {
+        if edge.from >= nodes.len() || edge.to >= nodes.len() {
+            continue;
+        }
+        let length = if edge.path.len() >= 2 {
+            edge.path.windows(2).map(|w| dist2(w[0], w[1]).sqrt()).sum()
+        } else {
+            dist2(locations[edge.from].location, locations[edge.to].location).sqrt()
+        };
+        graph.add_edge(nodes[edge.from], nodes[edge.to], length);
+    }
+
+    nodes
+        .iter()
+        .map(|&start| {
+            let reached = dijkstra(&graph, start, None, |e| *e.weight());
+            nodes.iter().map(|n| reached.get(n).copied().unwrap_or(f64::INFINITY)).collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Archetype, PlaceInfo};
+
+    #[test]
+    fn test_plan_roads_connects_every_place() {
+        let points = vec![(0.0, 0.0), (1.0, 0.0), (0.5, 0.8), (-0.6, 0.3), (0.2, -0.9)];
+        let pairs = plan_roads(&points);
+
+        // Flood fill from place 0 over the planned roads
+        let mut seen = vec![false; points.len()];
+        let mut stack = vec![0];
+        while let Some(i) = stack.pop() {
+            if seen[i] {
+                continue;
+            }
+            seen[i] = true;
+            for &(a, b) in &pairs {
+                if a == i { stack.push(b); }
+                if b == i { stack.push(a); }
+            }
+        }
+        assert!(seen.into_iter().all(|s| s));
+    }
+
+    #[test]
+    fn test_plan_roads_skips_blocked_pairs() {
+        // The middle place sits between the outer two, so they are not linked directly
+        let points = vec![(-1.0, 0.0), (0.0, 0.0), (1.0, 0.0)];
+        let pairs = plan_roads(&points);
+        assert_eq!(pairs, vec![(0, 1), (1, 2)]);
+    }
+
+    #[test]
+    fn test_plan_highways_spans_regions() {
+        let points = vec![(0.0, 0.0), (0.1, 0.0), (0.5, 0.0), (0.6, 0.0), (0.55, 0.5)];
+        let regions = vec![vec![0, 1], vec![2, 3], vec![4]];
+        let mut highways = plan_highways(&points, &regions);
+        highways.sort();
+        // Closest pairs: 1–2 joins the first two cities, 2–4 or 3–4 the third
+        assert_eq!(highways.len(), 2);
+        assert!(highways.contains(&(1, 2)));
+        assert!(highways.iter().any(|&(a, b)| (a == 2 || a == 3) && b == 4));
+    }
+
+    #[test]
+    fn test_curved_road_bows_between_places() {
+        let locations: Vec<Place> = [(0.0, 0.0), (1.0, 0.0)]
+            .into_iter()
+            .map(|location| Place { id: String::new(), name: String::new(), location, archetype: Archetype::Village, info: PlaceInfo::default() })
+            .collect();
+        let projection = Projection { origin: (0.0, 0.0), scale: 1.0 / 1000.0 };
+        let road = curved_road(&locations, 0, 1, Some(&projection), 1.0);
+
+        assert_eq!(road.path.first(), Some(&(0.0, 0.0)));
+        assert_eq!(road.path.last(), Some(&(1.0, 0.0)));
+        let middle = road.path[road.path.len() / 2];
+        assert!((middle.1 - 0.15).abs() < 1e-9);
+        assert!(road.length_m > 1000.0);
+    }
+
+    #[test]
+    fn test_road_distances_follow_paths() {
+        let locations: Vec<Place> = [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)]
+            .into_iter()
+            .map(|location| Place { id: String::new(), name: String::new(), location, archetype: Archetype::Village, info: PlaceInfo::default() })
+            .collect();
+        let edge = |from, to, path| RoadEdge { from, to, length_m: 0.0, travel_time_s: 0.0, highway: false, path };
+        let roads = RoadNetwork { edges: vec![edge(0, 1, vec![(0.0, 0.0), (0.5, 0.5), (1.0, 0.0)])] };
+        let distances = road_distances(&locations, &roads);
+
+        assert!((distances[0][1] - 2.0f64.sqrt()).abs() < 1e-9);
+        assert_eq!(distances[1][0], distances[0][1]);
+        assert_eq!(distances[0][0], 0.0);
+        assert!(distances[0][2].is_infinite());
+    }
+
+    #[test]
+    fn test_plan_roads_ignores_input_order() {
+        let points = vec![(0.0, 0.0), (1.0, 0.0), (0.5, 0.8), (-0.6, 0.3)];
+        let reversed: Vec<_> = points.iter().rev().cloned().collect();
+        let n = points.len();
+
+        let mut a = plan_roads(&points);
+        let mut b: Vec<_> = plan_roads(&reversed)
+            .into_iter()
+            .map(|(i, j)| ((n - 1 - j), (n - 1 - i)))
+            .collect();
+        a.sort();
+        b.sort();
+        assert_eq!(a, b);
+    }
+}
@@ -0,0 +1,137 @@
+/// Distance from `p` to the segment `a`–`b`
+fn segment_distance(p: (f64, f64), a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let len2 = dx * dx + dy * dy;
+    let t = if len2 > 0.0 {
+        (((p.0 - a.0) * dx + (p.1 - a.1) * dy) / len2).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    let (cx, cy) = (a.0 + t * dx, a.1 + t * dy);
+    ((p.0 - cx).powi(2) + (p.1 - cy).powi(2)).sqrt()
+}
+
+/// Point between `start` and `end` (exclusive) farthest from their chord
+fn farthest(points: &[(f64, f64)], start: usize, end: usize) -> Option<(usize, f64)> {
+    (start + 1..end)
+        .map(|i| (i, segment_distance(points[i], points[start], points[end])))
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+}
+
+/// Simplify a polyline with Douglas–Peucker, refining greedily so the point
+/// budget is spent where the shape deviates most.
+///
+/// Starts from the two endpoints and keeps adding the vertex farthest from the
+/// current approximation until every dropped vertex is within `tolerance`
+/// (in the same units as the points) or `max_points` vertices have been kept.
+/// Endpoints are always kept; corners are kept before points on straight runs.
+pub fn simplify_polyline(points: &[(f64, f64)], tolerance: f64, max_points: usize) -> Vec<(f64, f64)> {
+    if points.len() <= 2 {
+        return points.to_vec();
+    }
+
+    let last = points.len() - 1;
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[last] = true;
+    let mut kept = 2;
+
+    // Open spans between kept vertices, with the worst vertex inside each
+    let mut spans: Vec<(usize, usize, usize, f64)> = Vec::new();
+    if let Some((i, d)) = farthest(points, 0, last) {
+        spans.push((0, last, i, d));
+    }
+
+    while kept < max_points {
+        let Some(worst) = spans
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.3.total_cmp(&b.1.3))
+            .map(|(idx, _)| idx)
+        else {
+            break;
+        };
+        let (start, end, split, dist) = spans.swap_remove(worst);
+        if dist <= tolerance {
+            break;
+        }
+
+        keep[split] = true;
+        kept += 1;
+        for (a, b) in [(start, split), (split, end)] {
+            if let Some((i, d)) = farthest(points, a, b) {
+                spans.push((a, b, i, d));
+            }
+        }
+    }
+
+    points
+        .iter()
+        .zip(keep)
+        .filter(|(_, k)| *k)
+        .map(|(p, _)| *p)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keeps_endpoints() {
+        let points: Vec<(f64, f64)> = (0..50).map(|i| (i as f64 * 0.01, (i as f64 * 0.3).sin() * 0.05)).collect();
+        let simplified = simplify_polyline(&points, 0.001, 10);
+        assert_eq!(simplified.first(), points.first());
+        assert_eq!(simplified.last(), points.last());
+        assert!(simplified.len() <= 10);
+    }
+
+    #[test]
+    fn test_straight_road_collapses() {
+        let points: Vec<(f64, f64)> = (0..20).map(|i| (i as f64 * 0.05, i as f64 * 0.02)).collect();
+        let simplified = simplify_polyline(&points, 0.001, 32);
+        assert_eq!(simplified, vec![points[0], points[19]]);
+    }
+
+    #[test]
+    fn test_keeps_corner() {
+        // An L-shaped road: along x, then up y
+        let mut points: Vec<(f64, f64)> = (0..=10).map(|i| (i as f64 * 0.1, 0.0)).collect();
+        points.extend((1..=10).map(|i| (1.0, i as f64 * 0.1)));
+        let simplified = simplify_polyline(&points, 0.001, 32);
+        assert_eq!(simplified, vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0)]);
+
+        // Even on a tight budget the corner beats the points on the straights
+        let simplified = simplify_polyline(&points, 0.0, 3);
+        assert_eq!(simplified, vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0)]);
+    }
+
+    #[test]
+    fn test_shape_within_tolerance() {
+        let points: Vec<(f64, f64)> = (0..200)
+            .map(|i| {
+                let a = i as f64 * std::f64::consts::PI / 199.0;
+                (a.cos(), a.sin())
+            })
+            .collect();
+        let tolerance = 0.01;
+        let simplified = simplify_polyline(&points, tolerance, 200);
+
+        // Every original vertex stays close to the simplified line
+        for &p in &points {
+            let d = simplified
+                .windows(2)
+                .map(|w| segment_distance(p, w[0], w[1]))
+                .fold(f64::INFINITY, f64::min);
+            assert!(d <= tolerance + 1e-12);
+        }
+        assert!(simplified.len() < points.len() / 4);
+    }
+
+    #[test]
+    fn test_short_inputs() {
+        assert!(simplify_polyline(&[], 0.01, 8).is_empty());
+        assert_eq!(simplify_polyline(&[(0.5, 0.5)], 0.01, 8), vec![(0.5, 0.5)]);
+        assert_eq!(simplify_polyline(&[(0.0, 0.0), (1.0, 1.0)], 0.01, 8).len(), 2);
+    }
+}
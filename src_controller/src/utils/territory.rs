@@ -0,0 +1,181 @@
+use std::collections::BTreeMap;
+use serde::Serialize;
+
+use crate::types::{Map, Ownership};
+use crate::utils::geometry::{circle_polygon, clip_polygon_to_half_plane};
+
+/// Sides of the polygon standing in for the edge of the map
+const BOUNDARY_SEGMENTS: usize = 64;
+/// How far a vertex may sit from a bisector and still count as lying on it
+const ON_LINE_TOLERANCE: f64 = 1e-9;
+
+/// The land a faction holds: the Voronoi cell of every place it owns
+#[derive(Clone, Debug, Serialize)]
+pub struct Territory {
+    pub faction: String,
+    /// Indices into `Map.locations`; `cells[i]` surrounds `places[i]`
+    pub places: Vec<usize>,
+    pub cells: Vec<Vec<(f64, f64)>>,
+}
+
+/// A straight piece of border, from one end to the other
+pub type Segment = ((f64, f64), (f64, f64));
+
+/// Where two factions' territories meet
+#[derive(Clone, Debug, Serialize)]
+pub struct Border {
+    pub factions: (String, String),
+    pub segments: Vec<Segment>,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Territories {
+    pub territories: Vec<Territory>,
+    pub borders: Vec<Border>,
+    /// Factions each faction shares a border with
+    pub adjacency: BTreeMap<String, Vec<String>>,
+}
+
+/// Line of points equidistant from `a` and `b`, as `(normal, offset)` with
+/// the side nearer `a` being `normal · p <= offset`
+fn bisector(a: (f64, f64), b: (f64, f64)) -> ((f64, f64), f64) {
+    let normal = (b.0 - a.0, b.1 - a.1);
+    let offset = (b.0 * b.0 + b.1 * b.1 - a.0 * a.0 - a.1 * a.1) / 2.0;
+    (normal, offset)
+}
+
+/// Part of the unit circle nearer `points[i]` than any other point
+fn voronoi_cell(points: &[(f64, f64)], i: usize) -> Vec<(f64, f64)> {
+    let mut cell = circle_polygon(1.0, BOUNDARY_SEGMENTS);
+    for (j, &other) in points.iter().enumerate() {
+        // Places on top of each other would split nothing
+        if j == i || other == points[i] || cell.is_empty() {
+            continue;
+        }
+        let (normal, offset) = bisector(points[i], other);
+        cell = clip_polygon_to_half_plane(&cell, normal, offset);
+    }
+    cell
+}
+
+/// Work out the territory each faction holds, where neighbouring territories
+/// meet and which factions border each other.
+///
+/// Every place owns its Voronoi cell within the unit circle, so a faction's
+/// territory is the cells of its places. Unowned places hold no territory.
+pub fn faction_territories(map: &Map, ownership: &Ownership) -> Territories {
+    let points: Vec<(f64, f64)> = map.locations.iter().map(|p| p.location).collect();
+    let faction_of: Vec<Option<&String>> = map.locations.iter().map(|p| ownership.get(p)).collect();
+    let cells: Vec<Vec<(f64, f64)>> = (0..points.len()).map(|i| voronoi_cell(&points, i)).collect();
+
+    // Step 1: Group cells by faction
+    let mut territories: BTreeMap<&String, Territory> = BTreeMap::new();
+    for (i, faction) in faction_of.iter().enumerate() {
+        let Some(faction) = faction else {
+            continue;
+        };
+        let territory = territories.entry(faction).or_insert_with(|| Territory {
+            faction: faction.to_string(),
+            places: Vec::new(),
+            cells: Vec::new(),
+        });
+        territory.places.push(i);
+        territory.cells.push(cells[i].clone());
+    }
+
+    // Step 2: Cells of different factions meet along their bisector
+    let mut borders: BTreeMap<(&String, &String), Vec<Segment>> = BTreeMap::new();
+    for i in 0..points.len() {
+        for j in (i + 1)..points.len() {
+            let (Some(a), Some(b)) = (faction_of[i], faction_of[j]) else {
+                continue;
+            };
+            if a == b || points[i] == points[j] {
+                continue;
+            }
+            let (normal, offset) = bisector(points[i], points[j]);
+            let length = (normal.0 * normal.0 + normal.1 * normal.1).sqrt();
+            let on_line = |p: (f64, f64)| ((normal.0 * p.0 + normal.1 * p.1 - offset) / length).abs() < ON_LINE_TOLERANCE;
+
+            let cell = &cells[i];
+            for k in 0..cell.len() {
+                let (p, q) = (cell[k], cell[(k + 1) % cell.len()]);
+                if p != q && on_line(p) && on_line(q) {
+                    borders.entry((a.min(b), a.max(b))).or_default().push((p, q));
+                }
+            }
+        }
+    }
+
+    // Step 3: Factions that share any border are neighbours
+    let mut adjacency: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for faction in territories.keys() {
+        adjacency.insert(faction.to_string(), Vec::new());
+    }
+    for &(a, b) in borders.keys() {
+        adjacency.entry(a.clone()).or_default().push(b.clone());
+        adjacency.entry(b.clone()).or_default().push(a.clone());
+    }
+
+    Territories {
+        territories: territories.into_values().collect(),
+        borders: borders
+            .into_iter()
+            .map(|((a, b), segments)| Border { factions: (a.clone(), b.clone()), segments })
+            .collect(),
+        adjacency,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Archetype, Place, PlaceInfo, RoadNetwork};
+
+    #[test]
+    fn test_two_factions_split_down_the_middle() {
+        let map = Map {
+            locations: vec![
+                Place { id: "p0".to_string(), name: "P0".to_string(), location: (-0.5, 0.0), archetype: Archetype::Village, info: PlaceInfo::default() },
+                Place { id: "p1".to_string(), name: "P1".to_string(), location: (0.5, 0.0), archetype: Archetype::Village, info: PlaceInfo::default() },
+            ],
+            roads: RoadNetwork { edges: vec![] },
+            projection: None,
+            features: vec![],
+            regions: vec![],
+        };
+        let ownership: Ownership = map.locations.iter().cloned().zip(["g", "t"].map(String::from)).collect();
+        let t = faction_territories(&map, &ownership);
+
+        assert_eq!(t.territories.len(), 2);
+        assert!(t.territories[0].cells[0].iter().all(|p| p.0 <= 1e-9));
+        assert_eq!(t.borders.len(), 1);
+        let ((x1, y1), (x2, y2)) = t.borders[0].segments[0];
+        assert!(x1.abs() < 1e-9 && x2.abs() < 1e-9);
+        assert!(y1.abs() > 0.99 && y2.abs() > 0.99);
+        assert_eq!(t.adjacency["g"], vec!["t".to_string()]);
+    }
+
+    #[test]
+    fn test_factions_apart_are_not_adjacent() {
+        let map = Map {
+            locations: vec![
+                Place { id: "p0".to_string(), name: "P0".to_string(), location: (-0.6, 0.0), archetype: Archetype::Village, info: PlaceInfo::default() },
+                Place { id: "p1".to_string(), name: "P1".to_string(), location: (-0.3, 0.0), archetype: Archetype::Village, info: PlaceInfo::default() },
+                Place { id: "p2".to_string(), name: "P2".to_string(), location: (0.0, 0.0), archetype: Archetype::Village, info: PlaceInfo::default() },
+                Place { id: "p3".to_string(), name: "P3".to_string(), location: (0.6, 0.0), archetype: Archetype::Village, info: PlaceInfo::default() },
+            ],
+            roads: RoadNetwork { edges: vec![] },
+            projection: None,
+            features: vec![],
+            regions: vec![],
+        };
+        let ownership: Ownership = map.locations.iter().cloned().zip(["g", "g", "t", "c"].map(String::from)).collect();
+        let t = faction_territories(&map, &ownership);
+
+        // Both gnome cells are kept, but the border between them is not a border
+        assert_eq!(t.territories.iter().find(|t| t.faction == "g").unwrap().cells.len(), 2);
+        assert_eq!(t.adjacency["t"], vec!["c".to_string(), "g".to_string()]);
+        assert!(!t.adjacency["g"].contains(&"c".to_string()));
+    }
+}
@@ -0,0 +1,388 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::types::Map;
+use crate::utils::ids::make_id;
+use crate::utils::roads::{components, straight_road};
+
+/// Places and route points further than this from the centre are out of bounds
+const BOUNDS_RADIUS: f64 = 1.0;
+/// Radius places are pulled back to when the map has to be rescaled
+const RESCALE_RADIUS: f64 = 0.9;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MapIssue {
+    /// No places at all; nothing else can be checked
+    NoPlaces,
+    DuplicateName { place: usize, name: String },
+    DuplicateId { place: usize, id: String },
+    NonFiniteLocation { place: usize },
+    PlaceOutOfBounds { place: usize, distance: f64 },
+    InvalidRoadEndpoint { road: usize },
+    NonFiniteRoutePoints { road: usize, count: usize },
+    RoutePointsOutOfBounds { road: usize, count: usize },
+    EmptyRoute { road: usize },
+    DisconnectedPlace { place: usize },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Repair {
+    RemovedPlace,
+    RemovedRoad,
+    DroppedPoints,
+    ClampedPoints,
+    StraightenedRoute,
+    /// Every coordinate in the map was multiplied by this factor
+    RescaledMap { factor: f64 },
+    Renamed { to: String },
+    /// Given a new id, leaving the first place with the old one
+    NewId { to: String },
+    /// Joined to this place with a straight road
+    AddedRoad { to: usize },
+}
+
+/// An issue found in a map and, if it was safe to fix, what was done about it.
+/// Indices refer to the map as it was when the issue was found.
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub issue: MapIssue,
+    pub repair: Option<Repair>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MapReport {
+    pub findings: Vec<Finding>,
+}
+
+impl MapReport {
+    fn push(&mut self, issue: MapIssue, repair: Option<Repair>) {
+        self.findings.push(Finding { issue, repair });
+    }
+
+    /// True when every issue found was repaired
+    pub fn is_usable(&self) -> bool {
+        self.findings.iter().all(|f| f.repair.is_some())
+    }
+}
+
+impl fmt::Display for MapReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.findings.is_empty() {
+            return writeln!(f, "Map validation: no issues");
+        }
+        writeln!(f, "Map validation: {} issue(s)", self.findings.len())?;
+        for finding in &self.findings {
+            match &finding.repair {
+                Some(repair) => writeln!(f, "  {:?} -> repaired: {:?}", finding.issue, repair)?,
+                None => writeln!(f, "  {:?} -> not repaired", finding.issue)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn is_finite(p: (f64, f64)) -> bool {
+    p.0.is_finite() && p.1.is_finite()
+}
+
+fn length(p: (f64, f64)) -> f64 {
+    (p.0 * p.0 + p.1 * p.1).sqrt()
+}
+
+/// Check a map before it is clustered and sent, repairing what is safe to repair.
+///
+/// Covers non-finite coordinates, roads pointing at missing places, empty
+/// routes, points outside the unit circle, duplicate place names and ids, and
+/// places with no road to the rest of the map.
+pub fn validate_map(map: &mut Map) -> MapReport {
+    let mut report = MapReport::default();
+
+    // Step 1: Drop places with non-finite coordinates, remapping road endpoints
+    let mut new_index: Vec<Option<usize>> = Vec::with_capacity(map.locations.len());
+    let mut kept = 0;
+    for (i, place) in map.locations.iter().enumerate() {
+        if is_finite(place.location) {
+            new_index.push(Some(kept));
+            kept += 1;
+        } else {
+            report.push(MapIssue::NonFiniteLocation { place: i }, Some(Repair::RemovedPlace));
+            new_index.push(None);
+        }
+    }
+    map.locations.retain(|p| is_finite(p.location));
+    for region in map.regions.iter_mut() {
+        region.places = region.places.iter().filter_map(|&i| new_index.get(i).copied().flatten()).collect();
+    }
+
+    if map.locations.is_empty() {
+        report.push(MapIssue::NoPlaces, None);
+        return report;
+    }
+
+    // Step 2: Drop roads that no longer join two distinct places
+    let mut road = 0;
+    map.roads.edges.retain_mut(|edge| {
+        let ends = (
+            new_index.get(edge.from).copied().flatten(),
+            new_index.get(edge.to).copied().flatten(),
+        );
+        let keep = match ends {
+            (Some(from), Some(to)) if from != to => {
+                edge.from = from;
+                edge.to = to;
+                true
+            }
+            _ => {
+                report.push(MapIssue::InvalidRoadEndpoint { road }, Some(Repair::RemovedRoad));
+                false
+            }
+        };
+        road += 1;
+        keep
+    });
+
+    // Step 3: Clean up route geometry
+    for (road, edge) in map.roads.edges.iter_mut().enumerate() {
+        let before = edge.path.len();
+        edge.path.retain(|&p| is_finite(p));
+        if edge.path.len() < before {
+            report.push(
+                MapIssue::NonFiniteRoutePoints { road, count: before - edge.path.len() },
+                Some(Repair::DroppedPoints),
+            );
+        }
+        if edge.path.len() < 2 {
+            edge.path = vec![map.locations[edge.from].location, map.locations[edge.to].location];
+            report.push(MapIssue::EmptyRoute { road }, Some(Repair::StraightenedRoute));
+        }
+    }
+
+    // Step 4: Pull places back inside the unit circle by rescaling the whole map,
+    // which keeps its shape and keeps the projection consistent
+    let max_place = map.locations.iter().map(|p| length(p.location)).fold(0.0, f64::max);
+    if max_place > BOUNDS_RADIUS {
+        let factor = RESCALE_RADIUS / max_place;
+        for (i, place) in map.locations.iter().enumerate() {
+            let distance = length(place.location);
+            if distance > BOUNDS_RADIUS {
+                report.push(MapIssue::PlaceOutOfBounds { place: i, distance }, Some(Repair::RescaledMap { factor }));
+            }
+        }
+
+        let scale = |p: &mut (f64, f64)| *p = (p.0 * factor, p.1 * factor);
+        map.locations.iter_mut().for_each(|p| scale(&mut p.location));
+        map.roads.edges.iter_mut().for_each(|e| e.path.iter_mut().for_each(scale));
+        map.features.iter_mut().for_each(|f| f.geometry.iter_mut().for_each(scale));
+        if let Some(projection) = map.projection.as_mut() {
+            projection.scale *= factor;
+        }
+    }
+
+    // Step 5: Roads may still bulge past the edge; clamp those points onto it
+    for (road, edge) in map.roads.edges.iter_mut().enumerate() {
+        let mut count = 0;
+        for p in edge.path.iter_mut() {
+            let d = length(*p);
+            if d > BOUNDS_RADIUS {
+                *p = (p.0 / d * BOUNDS_RADIUS, p.1 / d * BOUNDS_RADIUS);
+                count += 1;
+            }
+        }
+        if count > 0 {
+            report.push(MapIssue::RoutePointsOutOfBounds { road, count }, Some(Repair::ClampedPoints));
+        }
+    }
+
+    // Step 6: Duplicate names would be impossible to tell apart on the map
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    for (i, place) in map.locations.iter_mut().enumerate() {
+        let count = seen.entry(place.name.clone()).or_insert(0);
+        *count += 1;
+        if *count > 1 {
+            let original = place.name.clone();
+            let mut n = *count;
+            let mut renamed = format!("{} ({})", original, n);
+            while seen.contains_key(&renamed) {
+                n += 1;
+                renamed = format!("{} ({})", original, n);
+            }
+            seen.insert(renamed.clone(), 1);
+            place.name = renamed.clone();
+            report.push(MapIssue::DuplicateName { place: i, name: original }, Some(Repair::Renamed { to: renamed }));
+        }
+    }
+
+    // Step 7: Ownership and events refer to places by id, so each must be unique
+    let mut ids: HashSet<String> = HashSet::new();
+    for (i, place) in map.locations.iter_mut().enumerate() {
+        if ids.insert(place.id.clone()) {
+            continue;
+        }
+        let original = place.id.clone();
+        let mut n = 2;
+        let mut id = make_id("pl", &format!("{}#{}", original, n));
+        while ids.contains(&id) {
+            n += 1;
+            id = make_id("pl", &format!("{}#{}", original, n));
+        }
+        ids.insert(id.clone());
+        place.id = id.clone();
+        report.push(MapIssue::DuplicateId { place: i, id: original }, Some(Repair::NewId { to: id }));
+    }
+
+    // Step 8: Join every other component to the largest with a straight road
+    // between their closest pair of places
+    loop {
+        let component = components(map.locations.len(), &map.roads);
+        let mut sizes: HashMap<usize, usize> = HashMap::new();
+        for &c in &component {
+            *sizes.entry(c).or_insert(0) += 1;
+        }
+        if sizes.len() <= 1 {
+            break;
+        }
+        let main = *sizes.iter().max_by_key(|(c, size)| (**size, std::cmp::Reverse(**c))).unwrap().0;
+
+        let (from, to) = (0..map.locations.len())
+            .filter(|&i| component[i] != main)
+            .flat_map(|i| (0..map.locations.len()).filter(|&j| component[j] == main).map(move |j| (i, j)))
+            .min_by(|&(a, b), &(c, d)| {
+                let dist = |i: usize, j: usize| length((
+                    map.locations[i].location.0 - map.locations[j].location.0,
+                    map.locations[i].location.1 - map.locations[j].location.1,
+                ));
+                dist(a, b).total_cmp(&dist(c, d))
+            })
+            .unwrap();
+
+        // Report every place in the component being joined
+        for i in (0..map.locations.len()).filter(|&i| component[i] == component[from]) {
+            report.push(MapIssue::DisconnectedPlace { place: i }, Some(Repair::AddedRoad { to }));
+        }
+        let road = straight_road(&map.locations, from, to, map.projection.as_ref());
+        map.roads.edges.push(road);
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Archetype, Place, PlaceInfo, RoadEdge, RoadNetwork};
+
+    #[test]
+    fn test_clean_map_has_no_findings() {
+        let mut m = Map {
+            locations: vec![
+                Place { id: "A".to_string(), name: "A".to_string(), location: (0.0, 0.0), archetype: Archetype::Village, info: PlaceInfo::default() },
+                Place { id: "B".to_string(), name: "B".to_string(), location: (0.5, 0.0), archetype: Archetype::Village, info: PlaceInfo::default() },
+            ],
+            roads: RoadNetwork {
+                edges: vec![
+                    RoadEdge { from: 0, to: 1, length_m: 0.0, travel_time_s: 0.0, highway: false, path: vec![(0.0, 0.0), (0.5, 0.0)] },
+                ],
+            },
+            projection: None,
+            features: vec![],
+            regions: vec![],
+        };
+        let report = validate_map(&mut m);
+        assert!(report.findings.is_empty());
+    }
+
+    #[test]
+    fn test_non_finite_place_removed_and_roads_remapped() {
+        let mut m = Map {
+            locations: vec![
+                Place { id: "A".to_string(), name: "A".to_string(), location: (0.0, 0.0), archetype: Archetype::Village, info: PlaceInfo::default() },
+                Place { id: "Bad".to_string(), name: "Bad".to_string(), location: (f64::NAN, 0.0), archetype: Archetype::Village, info: PlaceInfo::default() },
+                Place { id: "C".to_string(), name: "C".to_string(), location: (0.5, 0.0), archetype: Archetype::Village, info: PlaceInfo::default() },
+            ],
+            roads: RoadNetwork {
+                edges: vec![
+                    RoadEdge { from: 0, to: 1, length_m: 0.0, travel_time_s: 0.0, highway: false, path: vec![(0.0, 0.0), (0.1, 0.0)] },
+                    RoadEdge { from: 0, to: 2, length_m: 0.0, travel_time_s: 0.0, highway: false, path: vec![(0.0, 0.0), (0.5, 0.0)] },
+                ],
+            },
+            projection: None,
+            features: vec![],
+            regions: vec![],
+        };
+        let report = validate_map(&mut m);
+
+        assert_eq!(m.locations.len(), 2);
+        assert_eq!(m.roads.edges.len(), 1);
+        assert_eq!((m.roads.edges[0].from, m.roads.edges[0].to), (0, 1));
+        assert!(report.is_usable());
+        assert!(report.findings.iter().any(|f| f.issue == MapIssue::NonFiniteLocation { place: 1 }));
+    }
+
+    #[test]
+    fn test_duplicates_out_of_bounds_and_disconnected() {
+        let mut m = Map {
+            locations: vec![
+                Place { id: "A".to_string(), name: "A".to_string(), location: (0.0, 0.0), archetype: Archetype::Village, info: PlaceInfo::default() },
+                Place { id: "A".to_string(), name: "A".to_string(), location: (2.0, 0.0), archetype: Archetype::Village, info: PlaceInfo::default() },
+                Place { id: "C".to_string(), name: "C".to_string(), location: (0.0, 0.5), archetype: Archetype::Village, info: PlaceInfo::default() },
+            ],
+            roads: RoadNetwork {
+                edges: vec![
+                    RoadEdge { from: 0, to: 2, length_m: 0.0, travel_time_s: 0.0, highway: false, path: vec![] },
+                ],
+            },
+            projection: None,
+            features: vec![],
+            regions: vec![],
+        };
+        let report = validate_map(&mut m);
+
+        assert_eq!(m.locations[1].name, "A (2)");
+        assert!(m.locations.iter().all(|p| length(p.location) <= 1.0));
+        assert_eq!(components(3, &m.roads).iter().collect::<std::collections::HashSet<_>>().len(), 1);
+        assert!(m.roads.edges.iter().all(|e| e.path.len() >= 2));
+        assert!(report.is_usable());
+    }
+
+    #[test]
+    fn test_duplicate_ids_get_new_ones() {
+        let mut m = Map {
+            locations: vec![
+                Place { id: "A".to_string(), name: "A".to_string(), location: (0.0, 0.0), archetype: Archetype::Village, info: PlaceInfo::default() },
+                Place { id: "B".to_string(), name: "B".to_string(), location: (0.5, 0.0), archetype: Archetype::Village, info: PlaceInfo::default() },
+                Place { id: "A".to_string(), name: "C".to_string(), location: (0.0, 0.5), archetype: Archetype::Village, info: PlaceInfo::default() },
+            ],
+            roads: RoadNetwork {
+                edges: vec![
+                    RoadEdge { from: 0, to: 1, length_m: 0.0, travel_time_s: 0.0, highway: false, path: vec![(0.0, 0.0), (0.5, 0.0)] },
+                    RoadEdge { from: 0, to: 2, length_m: 0.0, travel_time_s: 0.0, highway: false, path: vec![(0.0, 0.0), (0.0, 0.5)] },
+                ],
+            },
+            projection: None,
+            features: vec![],
+            regions: vec![],
+        };
+        let report = validate_map(&mut m);
+
+        assert_eq!(m.locations[0].id, "A");
+        assert_ne!(m.locations[2].id, "A");
+        assert_eq!(m.locations[2].name, "C");
+        assert!(report.is_usable());
+        assert_eq!(
+            report.findings.iter().map(|f| &f.issue).collect::<Vec<_>>(),
+            vec![&MapIssue::DuplicateId { place: 2, id: "A".to_string() }]
+        );
+    }
+
+    #[test]
+    fn test_empty_map_is_not_usable() {
+        let mut m = Map {
+            locations: vec![],
+            roads: RoadNetwork { edges: vec![] },
+            projection: None,
+            features: vec![],
+            regions: vec![],
+        };
+        assert!(!validate_map(&mut m).is_usable());
+    }
+}
@@ -1,9 +1,11 @@
 use plotters::prelude::*;
 use std::error::Error;
 use std::f64::consts::PI;
-use crate::types::{Map, Event, Ownership};
+use crate::types::{FeatureKind, Map, Event, Ownership};
+use crate::utils::factions::factions;
+use crate::utils::territory::Territories;
 
-pub fn viz_map(map: &Map, ownership: &Ownership) -> Result<(), Box<dyn Error>> {
+pub fn viz_map(map: &Map, ownership: &Ownership, territories: &Territories) -> Result<(), Box<dyn Error>> {
     // Create drawing area
     let root = BitMapBackend::new("map.png", (800, 800)).into_drawing_area();
     root.fill(&WHITE)?;
@@ -24,18 +26,47 @@ pub fn viz_map(map: &Map, ownership: &Ownership) -> Result<(), Box<dyn Error>> {
         .collect();
     chart.draw_series(LineSeries::new(circle_points, &BLACK))?;
 
-    // --- Plot routes ---
-    for route in &map.routes {
-        chart.draw_series(LineSeries::new(route.clone(), &BLUE.mix(0.6)))?;
+    // Helper to get color by faction; places held by an unknown faction are grey
+    let faction_color = |f: &String| match factions().get(f) {
+        Some(faction) => RGBColor(faction.colour.0, faction.colour.1, faction.colour.2),
+        None => RGBColor(128, 128, 128),
+    };
+
+    // --- Shade faction territories underneath everything else ---
+    for territory in &territories.territories {
+        let colour = faction_color(&territory.faction);
+        for cell in &territory.cells {
+            chart.draw_series(std::iter::once(Polygon::new(cell.clone(), colour.mix(0.15).filled())))?;
+        }
     }
 
-    // Helper to get color by faction
-    let faction_color = |f: &String| match f.as_str() {
-        "g" => &GREEN,
-        "t" => &RED,
-        "c" => &MAGENTA,
-        _ => panic!("Unknown faction")
-    };
+    // --- Plot terrain layers ---
+    for feature in &map.features {
+        let colour = match feature.kind {
+            FeatureKind::River | FeatureKind::Lake => RGBColor(100, 149, 237),
+            FeatureKind::Forest => RGBColor(34, 139, 34),
+            FeatureKind::Coastline => RGBColor(70, 70, 70),
+        };
+        if feature.kind.is_area() {
+            chart.draw_series(std::iter::once(Polygon::new(feature.geometry.clone(), colour.mix(0.35).filled())))?;
+        } else {
+            chart.draw_series(LineSeries::new(feature.geometry.clone(), colour.stroke_width(2)))?;
+        }
+    }
+
+    // --- Plot borders between territories ---
+    for border in &territories.borders {
+        for &(a, b) in &border.segments {
+            chart.draw_series(LineSeries::new(vec![a, b], BLACK.mix(0.7).stroke_width(2)))?;
+        }
+    }
+
+    // --- Plot routes ---
+    for road in &map.roads.edges {
+        // Highways between cities are drawn heavier than streets
+        let width = if road.highway { 3 } else { 1 };
+        chart.draw_series(LineSeries::new(road.path.clone(), BLUE.mix(0.6).stroke_width(width)))?;
+    }
 
     // --- Plot locations colored by faction ---
     for place in &map.locations {
@@ -5,4 +5,4 @@ fade_to_room(rm_game);
 
 var city = oCitySelector.selected_city;
 show_debug_message("Current city: " + city);
-init_map(city)
\ No newline at end of file
+init_map(city, global.EDITED_MAP)
\ No newline at end of file
@@ -43,21 +43,12 @@ function draw_characters_circle(_x, _y, _r, _scale)
     }
 
 
-    // --- Copy + sort characters by race ---
+    // --- Copy + sort characters by faction, in registry order ---
     var chars = array_create(array_length(global.characters));
     array_copy(chars, 0, global.characters, 0, array_length(global.characters));
 
     array_sort(chars, function(a, b) {
-        var order = ["t", "g", "c"]; // Troll, Gnome, Centaur
-        var ai = -1;
-        var bi = -1;
-        for (var j = 0; j < array_length(order); j++) {
-            if (order[j] == a.faction) ai = j;
-            if (order[j] == b.faction) bi = j;
-        }
-        if (ai == -1) ai = 999;
-        if (bi == -1) bi = 999;
-        return ai - bi;
+        return get_faction_index(a.faction) - get_faction_index(b.faction);
     });
 
     var count = array_length(chars);
@@ -93,16 +84,7 @@ function draw_characters_circle(_x, _y, _r, _scale)
         if (c_prev.faction != c_curr.faction)
             continue;
 
-        var col;
-        switch (c_curr.faction)
-        {
-            case "g": col = make_color_rgb(0x56, 0x6D, 0x27); break;
-            case "t": col = make_color_rgb(0x72, 0x64, 0x7b); break;
-            case "c": col = make_color_rgb(0xb2, 0x88, 0x60); break;
-            default:  col = c_white; break;
-        }
-
-        draw_set_color(col);
+        draw_set_color(get_faction_colour(c_curr.faction));
         var p1 = positions[i - 1];
         var p2 = positions[i];
         draw_line_width(p1[0], p1[1], p2[0], p2[1], 10 * sc);
@@ -121,12 +103,7 @@ function draw_characters_circle(_x, _y, _r, _scale)
         var py = positions[i][1];
 
         // --- Faction colour ---
-        var col = c_white;
-        switch (c.faction) {
-            case "g": col = make_color_rgb(0x56, 0x6D, 0x27); break;
-            case "t": col = make_color_rgb(0x72, 0x64, 0x7b); break;
-            case "c": col = make_color_rgb(0xb2, 0x88, 0x60); break;
-        }
+        var col = get_faction_colour(c.faction);
         draw_set_color(col);
 
         // --- Partial scaling ---
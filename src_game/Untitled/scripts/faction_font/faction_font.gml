@@ -1,13 +1,10 @@
-/// @function get_faction_sprite(code)
-/// @desc Maps a single-character faction code to its sprite.
-/// @param {string} code - "g" = Gnome, "t" = Troll, "c" = Centaur
+/// @function get_faction_font(code)
+/// @desc The font named by the faction's registry entry, falling back to fnt_troll.
+/// @param {string} code - Faction id, e.g. "g"
 function get_faction_font(code)
 {
-    switch (string_lower(code))
-    {
-        case "g": return fnt_troll;
-        case "t": return fnt_troll;
-        case "c": return fnt_troll;
-        default:  return spr_gnome;
-    }
+    var faction = get_faction(code);
+    if (is_undefined(faction) || !is_string(faction[$ "font"])) return fnt_troll;
+    var fnt = asset_get_index(faction.font);
+    return font_exists(fnt) ? fnt : fnt_troll;
 }
@@ -1,13 +1,41 @@
+/// @function get_faction(code)
+/// @desc Looks up a faction in the registry the server sent with INIT_MAP.
+/// @param {string} code - Faction id, e.g. "g"
+/// @returns {struct} The faction, or undefined if the server never sent it.
+function get_faction(code)
+{
+    for (var i = 0; i < array_length(global.factions); i++) {
+        if (global.factions[i][$ "id"] == code) return global.factions[i];
+    }
+    return undefined;
+}
+
+/// @function get_faction_index(code)
+/// @desc Position of a faction in the registry, or 999 if it isn't there, for sorting.
+function get_faction_index(code)
+{
+    for (var i = 0; i < array_length(global.factions); i++) {
+        if (global.factions[i][$ "id"] == code) return i;
+    }
+    return 999;
+}
+
+/// @function get_faction_colour(code)
+/// @desc The faction's registry colour, or white if unknown.
+function get_faction_colour(code)
+{
+    var faction = get_faction(code);
+    if (is_undefined(faction)) return c_white;
+    return make_color_rgb(faction.colour[0], faction.colour[1], faction.colour[2]);
+}
+
 /// @function get_faction_sprite(code)
-/// @desc Maps a single-character faction code to its sprite.
-/// @param {string} code - "g" = Gnome, "t" = Troll, "c" = Centaur
+/// @desc The sprite named by the faction's emblem, falling back to the gnome.
+/// @param {string} code - Faction id, e.g. "g"
 function get_faction_sprite(code)
 {
-    switch (string_lower(code))
-    {
-        case "g": return spr_gnome;
-        case "t": return spr_troll;
-        case "c": return spr_centaur;
-        default:  return spr_gnome;
-    }
+    var faction = get_faction(code);
+    if (is_undefined(faction)) return spr_gnome;
+    var spr = asset_get_index(faction.emblem);
+    return sprite_exists(spr) ? spr : spr_gnome;
 }
@@ -3,11 +3,32 @@ function gen_events(n) {
         var t_buffer = buffer_create(4096, buffer_grow, 1);
         buffer_seek(t_buffer, buffer_seek_start, 0);
 
+        // Who holds each place, and each place's name and archetype, all keyed by place id
+        var ownership = {};
+        var places = {};
+        var archetypes = {};
+        if (variable_global_exists("map") && is_struct(global.map)) {
+            if (variable_struct_exists(global.map, "ownership")) {
+                ownership = global.map.ownership;
+            }
+            for (var i = 0; i < array_length(global.map.locations); i++) {
+                var place = global.map.locations[i];
+                if (place.place_id == "") continue;
+                places[$ place.place_id] = place.name;
+                if (!is_undefined(place.archetype)) {
+                    archetypes[$ place.place_id] = place.archetype;
+                }
+            }
+        }
+
         // Build a struct instead of concatenating strings
         var data = {
             GEN_EVENTS: {
                 events: global.events,
                 characters: global.characters,
+                ownership: ownership,
+                places: places,
+                archetypes: archetypes,
                 n: n
             }
         };
@@ -1,9 +1,13 @@
-function init_map(location) {
+/// @function init_map(location, [edited_map])
+/// @param location    City to build the realm around.
+/// @param edited_map  Load the hand-edited map.geojson on the server instead of fetching a new map.
+function init_map(location, edited_map = false) {
     if (global.client_socket != undefined) {
         var t_buffer = buffer_create(256, buffer_grow, 1);
         buffer_seek(t_buffer, buffer_seek_start, 0);
 
-        var json_string = "{ \"INIT_MAP\": { \"loc_str\": \"" + string(location) + "\" } }";
+        var request = { INIT_MAP: { loc_str: string(location), edited_map: edited_map } };
+        var json_string = json_stringify(request);
         buffer_write(t_buffer, buffer_string, json_string);
         network_send_packet(global.client_socket, t_buffer, buffer_tell(t_buffer));
         buffer_delete(t_buffer);
@@ -102,13 +106,23 @@ function handle_init_map(data)
     var routes_raw  = data.map[1]; // second nested array = routes
     var ownership   = data.ownership;
 
+    // Colours, emblems and fonts all come from the server's faction registry
+    if (variable_struct_exists(data, "factions")) {
+        global.factions = data.factions;
+    }
+
     // --- Build Place structs ---
     var places = array_create(array_length(places_raw));
     for (var i = 0; i < array_length(places_raw); i++) {
-        var p = places_raw[i]; // e.g. ["Nottingham Castle", [-0.17,-0.50]]
+        var p = places_raw[i]; // e.g. ["Nottingham Castle", [-0.17,-0.50], { id, archetype, info }]
         var name = p[0];
         var coords = p[1];
-        places[i] = new Place(name, coords[0], coords[1]);
+        var meta = (array_length(p) > 2) ? p[2] : undefined;
+        if (is_struct(meta)) {
+            places[i] = new Place(name, coords[0], coords[1], meta[$ "id"], meta[$ "archetype"]);
+        } else {
+            places[i] = new Place(name, coords[0], coords[1]);
+        }
     }
 
     // --- Build Route arrays ---
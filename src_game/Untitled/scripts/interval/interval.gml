@@ -20,7 +20,10 @@ function init_faction_timeline()
         // --- Random number of ownership changes (0–3) ---
         var n_changes = irandom_range(0, 3);
 
-        var factions = [ "c", "g", "t" ]; // clerics, gnomes, traders for example
+        var factions = [];
+        for (var k = 0; k < array_length(global.factions); k++) {
+            array_push(factions, global.factions[k][$ "id"]);
+        }
         var last_faction = base_owner;
 
         for (var j = 0; j < n_changes; j++)
@@ -29,6 +32,7 @@ function init_faction_timeline()
             var new_faction;
 
             // pick a different faction than before
+            if (array_length(factions) == 0) break;
             repeat (10) {
                 new_faction = factions[irandom(array_length(factions) - 1)];
                 if (new_faction != last_faction) break;
@@ -82,5 +86,6 @@ function get_faction(location, time)
         }
     }
 
-    return "c"; // fallback
+    // Fallback: the first faction the server knows of
+    return (array_length(global.factions) > 0) ? global.factions[0][$ "id"] : "";
 }
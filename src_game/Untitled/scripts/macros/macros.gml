@@ -2,8 +2,10 @@ global.VIEWITME = 0;
 global.DEBUG_ENABLED = true;
 global.map_radius = 400;
 global.events = [];
+global.factions = [];      // faction registry, replaced by the one the server sends with INIT_MAP
 global.start_hovered = false;
 global.is_connected = false;
+global.EDITED_MAP = false; // start from the server's hand-edited map.geojson instead of fetching a new map
 
 
 
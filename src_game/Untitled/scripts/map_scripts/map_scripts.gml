@@ -18,11 +18,13 @@ function Vec2(_x, _y) constructor {
 
 
 
-/// @function Place(_name, _x, _y)
-/// @desc Place with a name and normalised coordinate
-function Place(_name, _x, _y) constructor {
+/// @function Place(_name, _x, _y, [_id], [_archetype])
+/// @desc Place with a name and normalised coordinate, plus the server's id and archetype when known
+function Place(_name, _x, _y, _id = "", _archetype = undefined) constructor {
     name = _name;
     loc  = new Vec2(_x, _y);
+    place_id = _id;
+    archetype = _archetype;
 }
 
 